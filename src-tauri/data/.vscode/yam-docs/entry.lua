@@ -1,4 +1,6 @@
 require("meta.keyboard")
 require("meta.mouse")
+require("meta.ui")
+require("meta.macro")
 require("meta.enum")
 require("meta.utils")
@@ -1,14 +1,52 @@
+---@meta
+
 ---@enum Direction
 Direction = {
-    Click = "Click",
     Press = "Press",
     Release = "Release",
+    Click = "Click",
 }
 
 Key = {}
 
 ---@enum Key.Send
 Key.Send = {
+    A = "A",
+    B = "B",
+    C = "C",
+    D = "D",
+    E = "E",
+    F = "F",
+    G = "G",
+    H = "H",
+    I = "I",
+    J = "J",
+    K = "K",
+    L = "L",
+    M = "M",
+    N = "N",
+    O = "O",
+    P = "P",
+    Q = "Q",
+    R = "R",
+    S = "S",
+    T = "T",
+    U = "U",
+    V = "V",
+    W = "W",
+    X = "X",
+    Y = "Y",
+    Z = "Z",
+    Key0 = "Key0",
+    Key1 = "Key1",
+    Key2 = "Key2",
+    Key3 = "Key3",
+    Key4 = "Key4",
+    Key5 = "Key5",
+    Key6 = "Key6",
+    Key7 = "Key7",
+    Key8 = "Key8",
+    Key9 = "Key9",
     F1 = "F1",
     F2 = "F2",
     F3 = "F3",
@@ -29,28 +67,43 @@ Key.Send = {
     F18 = "F18",
     F19 = "F19",
     F20 = "F20",
-    LeftShift = "LeftShift",
-    RightShift = "RightShift",
-    LeftCtrl = "LeftCtrl",
-    RightCtrl = "RightCtrl",
+    LShift = "LShift",
+    RShift = "RShift",
+    LCtrl = "LCtrl",
+    RCtrl = "RCtrl",
     Tab = "Tab",
     Space = "Space",
-    LeftCommand = "LeftCommand",
-    RightCommand = "RightCommand",
+    LCommand = "LCommand",
+    RCommand = "RCommand",
+    Alt = "Alt",
+    AltGr = "AltGr",
     Option = "Option",
+    Super = "Super",
+    Menu = "Menu",
     CapsLock = "CapsLock",
-    Up = "Up",
-    Down = "Down",
-    Left = "Left",
-    Right = "Right",
     Enter = "Enter",
     Esc = "Esc",
     Backspace = "Backspace",
     Delete = "Delete",
+    Up = "Up",
+    Down = "Down",
+    Left = "Left",
+    Right = "Right",
     Home = "Home",
     End = "End",
     PageUp = "PageUp",
     PageDown = "PageDown",
+    Insert = "Insert",
+    PrintScreen = "PrintScreen",
+    ScrollLock = "ScrollLock",
+    Pause = "Pause",
+    NumLock = "NumLock",
+    VolumeUp = "VolumeUp",
+    VolumeDown = "VolumeDown",
+    VolumeMute = "VolumeMute",
+    MediaPlayPause = "MediaPlayPause",
+    MediaNextTrack = "MediaNextTrack",
+    MediaPrevTrack = "MediaPrevTrack",
     Numpad0 = "Numpad0",
     Numpad1 = "Numpad1",
     Numpad2 = "Numpad2",
@@ -67,19 +120,22 @@ Key.Send = {
     NumpadDivide = "NumpadDivide",
     NumpadEnter = "NumpadEnter",
     NumpadDecimal = "NumpadDecimal",
+    NumpadEquals = "NumpadEquals",
+    Grave = "Grave",
+    Minus = "Minus",
+    Equal = "Equal",
+    LeftBracket = "LeftBracket",
+    RightBracket = "RightBracket",
+    BackSlash = "BackSlash",
+    Semicolon = "Semicolon",
+    Apostrophe = "Apostrophe",
+    Comma = "Comma",
+    Dot = "Dot",
+    Slash = "Slash",
 }
+
 ---@enum Key.Query
 Key.Query = {
-    Key0 = "Key0",
-    Key1 = "Key1",
-    Key2 = "Key2",
-    Key3 = "Key3",
-    Key4 = "Key4",
-    Key5 = "Key5",
-    Key6 = "Key6",
-    Key7 = "Key7",
-    Key8 = "Key8",
-    Key9 = "Key9",
     A = "A",
     B = "B",
     C = "C",
@@ -106,6 +162,16 @@ Key.Query = {
     X = "X",
     Y = "Y",
     Z = "Z",
+    Key0 = "Key0",
+    Key1 = "Key1",
+    Key2 = "Key2",
+    Key3 = "Key3",
+    Key4 = "Key4",
+    Key5 = "Key5",
+    Key6 = "Key6",
+    Key7 = "Key7",
+    Key8 = "Key8",
+    Key9 = "Key9",
     F1 = "F1",
     F2 = "F2",
     F3 = "F3",
@@ -126,34 +192,32 @@ Key.Query = {
     F18 = "F18",
     F19 = "F19",
     F20 = "F20",
-    Escape = "Escape",
-    Space = "Space",
-    LControl = "LControl",
-    RControl = "RControl",
     LShift = "LShift",
     RShift = "RShift",
-    LAlt = "LAlt",
-    RAlt = "RAlt",
-    Command = "Command",
+    LCtrl = "LCtrl",
+    RCtrl = "RCtrl",
+    Tab = "Tab",
+    Space = "Space",
+    LCommand = "LCommand",
     RCommand = "RCommand",
-    LOption = "LOption",
-    ROption = "ROption",
-    LMeta = "LMeta",
-    RMeta = "RMeta",
+    Alt = "Alt",
+    AltGr = "AltGr",
+    Option = "Option",
+    Super = "Super",
+    CapsLock = "CapsLock",
     Enter = "Enter",
+    Esc = "Esc",
+    Backspace = "Backspace",
+    Delete = "Delete",
     Up = "Up",
     Down = "Down",
     Left = "Left",
     Right = "Right",
-    Backspace = "Backspace",
-    CapsLock = "CapsLock",
-    Tab = "Tab",
     Home = "Home",
     End = "End",
     PageUp = "PageUp",
     PageDown = "PageDown",
     Insert = "Insert",
-    Delete = "Delete",
     Numpad0 = "Numpad0",
     Numpad1 = "Numpad1",
     Numpad2 = "Numpad2",
@@ -164,13 +228,13 @@ Key.Query = {
     Numpad7 = "Numpad7",
     Numpad8 = "Numpad8",
     Numpad9 = "Numpad9",
-    NumpadSubtract = "NumpadSubtract",
     NumpadAdd = "NumpadAdd",
-    NumpadDivide = "NumpadDivide",
+    NumpadSubtract = "NumpadSubtract",
     NumpadMultiply = "NumpadMultiply",
-    NumpadEquals = "NumpadEquals",
+    NumpadDivide = "NumpadDivide",
     NumpadEnter = "NumpadEnter",
     NumpadDecimal = "NumpadDecimal",
+    NumpadEquals = "NumpadEquals",
     Grave = "Grave",
     Minus = "Minus",
     Equal = "Equal",
@@ -182,6 +246,16 @@ Key.Query = {
     Comma = "Comma",
     Dot = "Dot",
     Slash = "Slash",
+    LControl = "LControl",
+    RControl = "RControl",
+    LMeta = "LMeta",
+    RMeta = "RMeta",
+    Command = "Command",
+    LAlt = "LAlt",
+    RAlt = "RAlt",
+    LOption = "LOption",
+    ROption = "ROption",
+    Escape = "Escape",
 }
 
 Button = {}
@@ -191,6 +265,8 @@ Button.Send = {
     Left = "Left",
     Right = "Right",
     Middle = "Middle",
+    Back = "Back",
+    Forward = "Forward",
     ScrollUp = "ScrollUp",
     ScrollDown = "ScrollDown",
     ScrollLeft = "ScrollLeft",
@@ -199,9 +275,11 @@ Button.Send = {
 
 ---@enum Button.Query
 Button.Query = {
-    Left = 1,
-    Right = 2,
-    Middle = 3,
+    Left = "Left",
+    Right = "Right",
+    Middle = "Middle",
+    Back = "Back",
+    Forward = "Forward",
 }
 
 ---@enum Coord
@@ -209,3 +287,12 @@ Coord = {
     Abs = "Abs",
     Rel = "Rel",
 }
+
+---@enum Curve
+Curve = {
+    Linear = "Linear",
+    EaseIn = "EaseIn",
+    EaseOut = "EaseOut",
+    EaseInOut = "EaseInOut",
+    Bezier = "Bezier",
+}
@@ -15,15 +15,76 @@ function keyboard.release(key) end
 
 ---指定されたキーを押して、離す
 ---@param key Key.Send キー
-function keyboard.click(key) end
+---@param opts? ClickOptions オプション
+function keyboard.click(key, opts) end
 
 ---指定されたキーが押されているかどうかを取得する
 ---@param key Key.Query キー
 ---@return boolean pressed 押されているかどうか
 function keyboard.is_pressing(key) end
 
---===== keyboard.char =====--
+---前回この場所で呼び出してからキーが押されたかどうかを取得する
+---呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない
+---@param key Key.Query キー
+---@param id? string 呼び出し元の代わりに使う識別子
+---@return boolean pressed 押されたかどうか
+function keyboard.just_pressed(key, id) end
+
+---前回この場所で呼び出してからキーが離されたかどうかを取得する
+---呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない
+---@param key Key.Query キー
+---@param id? string 呼び出し元の代わりに使う識別子
+---@return boolean released 離されたかどうか
+function keyboard.just_released(key, id) end
+
+---`input.token`を取得してからキーが押されたかどうかを取得する
+---@param key Key.Query キー
+---@param token integer `input.token`の戻り値
+---@return boolean pressed 押されたかどうか
+function keyboard.was_pressed_since(key, token) end
+
+---現在押されているすべてのキーを取得する
+---@return Key.Query[] keys 押されているキー
+function keyboard.pressed() end
 
+---@class LockState
+---@field caps_lock boolean Caps Lockが有効かどうか
+---@field num_lock boolean Num Lockが有効かどうか(macOSでは常にfalse)
+
+---Caps LockとNum Lockの状態を取得する
+---@return LockState state ロックキーの状態
+function keyboard.lock_state() end
+
+---キーの組み合わせを押す
+---左から順に押し、逆順に離す
+---
+---例: `keyboard.combo("LCtrl+LShift+s")`
+---@param spec string `+`区切りのキー名または文字
+function keyboard.combo(spec) end
+
+---AutoHotkey風の書式でキー入力を送る
+---
+---- `{Enter}` キーを押して離す
+---- `{LCtrl down}` / `{LCtrl up}` キーを押す / 離す
+---- `{Tab 3}` キーを3回押す
+---- `{{}` / `{}}` 波括弧そのもの
+---- それ以外の文字はそのまま入力される
+---
+---例: `keyboard.send("{LCtrl down}c{LCtrl up}{Enter}")`
+---@param spec string 送信する文字列
+function keyboard.send(spec) end
+
+---@class TypeOptions
+---@field delay_ms? integer 1文字ごとの待機時間 (ミリ秒, デフォルト: 0)
+---@field jitter? integer 待機時間に加えるランダムな揺らぎ (±ミリ秒, デフォルト: 0)
+
+---文字列を入力する
+---改行はEnter、タブはTabとして入力される
+---@param text string 入力する文字列
+---@param opts? TypeOptions オプション
+function keyboard.type(text, opts) end
+
+--===== keyboard.char =====--
 ---Enumにないキー用の関数
 keyboard.char = {}
 
@@ -0,0 +1,16 @@
+---@meta
+
+--===== macro =====--
+---記録したマクロ(JSON)を再生するモジュール
+---@class macro
+macro = {}
+
+---@class MacroPlayOptions
+---@field speed? number 再生速度の倍率(デフォルト: 1)
+---@field loops? integer 繰り返す回数(0で停止されるまで繰り返す、デフォルト: 1)
+
+---マクロファイルを再生する
+---相対パスはスクリプトのあるフォルダから解決される
+---@param path string マクロファイルのパス
+---@param opts? MacroPlayOptions オプション
+function macro.play(path, opts) end
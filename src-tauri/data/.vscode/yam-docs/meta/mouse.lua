@@ -16,21 +16,101 @@ function mouse.get_pos() end
 ---@param coord Coord 座標系
 function mouse.move(x, y, coord) end
 
+---@class MoveSmoothOptions
+---@field duration? integer 移動にかける時間(ミリ秒、デフォルト: 300)
+---@field curve? Curve 移動の曲線(デフォルト: "EaseInOut")
+---@field coord? Coord 座標系(デフォルト: "Abs")
+
+---マウスを指定した位置まで滑らかに移動する
+---@param x number x座標
+---@param y number y座標
+---@param opts? MoveSmoothOptions オプション
+function mouse.move_smooth(x, y, opts) end
+
+---@class ScrollOptions
+---@field duration? integer スクロールにかける時間(ミリ秒、デフォルト: 0で一度にスクロール)
+
+---マウスホイールをスクロールする
+---正の値で右/下、負の値で左/上にスクロールする
+---@param dx integer 横方向のスクロール量(ノッチ数)
+---@param dy integer 縦方向のスクロール量(ノッチ数)
+---@param opts? ScrollOptions オプション
+function mouse.scroll(dx, dy, opts) end
+
 ---マウスのボタンを押す
----@param button Button.Send | number ボタン
+---@param button Button.Send | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
 function mouse.press(button) end
 
 ---マウスのボタンを離す
----@param button Button.Send | number ボタン
+---@param button Button.Send | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
 function mouse.release(button) end
 
 ---マウスのボタンを押して、離す
----@param button Button.Send | number ボタン
-function mouse.click(button) end
+---@param button Button.Send | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
+---@param opts? ClickOptions オプション
+function mouse.click(button, opts) end
+
+---指定した位置に移動してクリックする
+---@param x number x座標
+---@param y number y座標
+---@param button? Button.Send | integer ボタン(デフォルト: "Left")
+function mouse.click_at(x, y, button) end
+
+---ダブルクリックする
+---@param button? Button.Send | integer ボタン(デフォルト: "Left")
+function mouse.double_click(button) end
+
+---指定した回数だけ連続でクリックする
+---@param count integer クリックする回数
+---@param interval? integer クリックの間隔(ミリ秒、デフォルト: 50)
+---@param button? Button.Send | integer ボタン(デフォルト: "Left")
+function mouse.multi_click(count, interval, button) end
+
+---@class Point
+---@field x number x座標
+---@field y number y座標
+
+---@class DragOptions
+---@field button? Button.Send | integer ボタン(デフォルト: "Left")
+---@field duration? integer 移動にかける時間(ミリ秒、デフォルト: 300)
+
+---ボタンを押したまま`from`から`to`まで移動する
+---途中でスクリプトが停止されてもボタンは離される
+---@param from Point | number[] 開始位置(絶対座標)
+---@param to Point | number[] 終了位置(絶対座標)
+---@param opts? DragOptions オプション
+function mouse.drag(from, to, opts) end
 
 ---マウスのボタンが押されているかどうかを取得する
----@param button Button.Query | number ボタンの番号
+---@param button Button.Query | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
 ---@return boolean pressed 押されているかどうか
 function mouse.is_pressing(button) end
 
-mouse = mouse
+---前回この場所で呼び出してからボタンが押されたかどうかを取得する
+---呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない
+---@param button Button.Query | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
+---@param id? string 呼び出し元の代わりに使う識別子
+---@return boolean pressed 押されたかどうか
+function mouse.just_pressed(button, id) end
+
+---前回この場所で呼び出してからボタンが離されたかどうかを取得する
+---呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない
+---@param button Button.Query | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
+---@param id? string 呼び出し元の代わりに使う識別子
+---@return boolean released 離されたかどうか
+function mouse.just_released(button, id) end
+
+---`input.token`を取得してからボタンが押されたかどうかを取得する
+---@param button Button.Query | integer ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)
+---@param token integer `input.token`の戻り値
+---@return boolean pressed 押されたかどうか
+function mouse.was_pressed_since(button, token) end
+
+---@class MouseState
+---@field x number x座標
+---@field y number y座標
+---@field buttons table<Button.Query, boolean> 各ボタンが押されているかどうか
+
+---マウスの位置とすべてのボタンの状態を取得する
+---@return MouseState state マウスの状態
+function mouse.state() end
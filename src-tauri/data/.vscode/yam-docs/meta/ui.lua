@@ -0,0 +1,45 @@
+---@meta
+
+--===== ui =====--
+---ダイアログ関連の関数を提供するモジュール
+---いずれの関数も応答があるまでスクリプトを停止し、その間にスクリプトが停止された場合は`nil`を返す
+---@class ui
+ui = {}
+
+---@class FileFilter
+---@field name string フィルタの名前
+---@field extensions string[] 拡張子の一覧
+
+---@class PickOptions
+---@field title? string タイトル
+---@field directory? string 初期ディレクトリ
+---@field filters? FileFilter[] ファイルのフィルタ(`ui.pick_file`のみ)
+
+---メッセージを表示する
+---@param message string メッセージ
+---@param title? string タイトル
+---@return boolean? closed 閉じられたかどうか
+function ui.alert(message, title) end
+
+---OK/キャンセルの確認ダイアログを表示する
+---@param message string メッセージ
+---@param title? string タイトル
+---@return boolean? ok OKが押されたかどうか
+function ui.confirm(message, title) end
+
+---文字列の入力ダイアログを表示する
+---@param message string メッセージ
+---@param default? string 初期値
+---@param title? string タイトル
+---@return string? value 入力された文字列(キャンセルされた場合は`nil`)
+function ui.prompt(message, default, title) end
+
+---ファイルの選択ダイアログを表示する
+---@param opts? PickOptions オプション
+---@return string? path 選択されたファイルのパス
+function ui.pick_file(opts) end
+
+---フォルダの選択ダイアログを表示する
+---@param opts? PickOptions オプション
+---@return string? path 選択されたフォルダのパス
+function ui.pick_folder(opts) end
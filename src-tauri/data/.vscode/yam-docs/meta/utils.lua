@@ -1,5 +1,75 @@
 ---@meta
 
---指定されたミリ秒数だけ待機する
+---指定されたミリ秒数だけ待機する
 ---@param ms number 待機するミリ秒数
-function sleep(ms) end
+---@param jitter? number 待機時間に加えるランダムな揺らぎ(±ミリ秒)
+function sleep(ms, jitter) end
+
+---指定されたミリ秒数だけ正確に待機する
+---最後の数ミリ秒はCPUを使って待つため、`sleep`より負荷が高い
+---@param ms number 待機するミリ秒数(小数も指定できる)
+function sleep_precise(ms) end
+
+---関数を一定の間隔で繰り返し呼ぶ
+---関数の処理時間を差し引いて次の呼び出しまで待つため、呼び出しの間隔はずれない
+---処理が間隔より長くかかった場合は、遅れた回を飛ばして次の間隔に合わせる
+---関数が`false`を返すと終了する
+---@param hz number 1秒あたりの呼び出し回数
+---@param f fun(tick: integer): boolean? 呼び出す関数(`tick`は1から数えた回数)
+function every(hz, f) end
+
+--===== time =====--
+---時間の計測に関する関数を提供するモジュール
+---時刻はスクリプトの開始からのミリ秒数で、一時停止していた時間は含まない
+---@class time
+time = {}
+
+---現在の時刻を取得する
+---ミリ秒未満の精度があり、時計の変更の影響を受けない
+---@return number ms スクリプトの開始からの経過時間(ミリ秒)
+function time.now() end
+
+---指定された時刻からの経過時間を取得する
+---@param t number `time.now`で取得した時刻
+---@return number ms 経過時間(ミリ秒)
+function time.since(t) end
+
+---@class HumanizeOptions
+---@field delay? integer 押している時間に加えるランダムな揺らぎ(ミリ秒、デフォルト: 40)
+---@field position? integer クリック位置に加えるランダムな揺らぎ(ピクセル、デフォルト: 2)
+
+---@class ClickOptions
+---@field humanize? boolean | HumanizeOptions 人間らしい揺らぎを加えるかどうか
+
+--===== humanize =====--
+---ランダムな揺らぎに関する関数を提供するモジュール
+---@class humanize
+humanize = {}
+
+---揺らぎに使う乱数のシードを設定する
+---同じシードを設定すると同じ揺らぎが再現される
+---@param seed integer シード
+function humanize.seed(seed) end
+
+--===== input =====--
+---ユーザーの入力に関する関数を提供するモジュール
+---@class input
+input = {}
+
+---ユーザーが最後にキーボードやマウスを操作してからの時間を取得する
+---@return integer ms 経過時間(ミリ秒)
+function input.idle_ms() end
+
+---現在の時点を表すトークンを取得する
+---`keyboard.was_pressed_since`などに渡して、それ以降に押されたかどうかを調べる
+---@return integer token トークン
+function input.token() end
+
+---@class NotifyOptions
+---@field icon? string アイコンのパス
+
+---デスクトップ通知を表示する
+---@param title string タイトル
+---@param body? string 本文
+---@param opts? NotifyOptions オプション
+function notify(title, body, opts) end
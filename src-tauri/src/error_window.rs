@@ -0,0 +1,224 @@
+use std::{
+    collections::VecDeque,
+    io,
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use tauri::{
+    menu::{MenuId, MenuItem, Submenu},
+    AppHandle, Manager, Url, WebviewUrl, WebviewWindowBuilder, Wry,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_opener::OpenerExt;
+
+use crate::{
+    lua::ErrorReport,
+    prompt::{encode_uri, escape_html, fill},
+    settings::Settings,
+};
+
+// トレイの履歴に残す件数
+const HISTORY_SIZE: usize = 10;
+// 履歴のメニューに表示するメッセージの長さ
+const LABEL_LENGTH: usize = 40;
+
+static ERROR_WINDOW_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub fn show_error_report(app: &AppHandle, report: ErrorReport) -> anyhow::Result<()> {
+    let label = format!("error-{}", ERROR_WINDOW_ID.fetch_add(1, Ordering::SeqCst));
+    let url = Url::parse(&format!(
+        "data:text/html;charset=utf-8,{}",
+        encode_uri(&render_html(&report))
+    ))?;
+
+    WebviewWindowBuilder::new(app, &label, WebviewUrl::External(url))
+        .title(format!("エラー - {}", report.title))
+        .inner_size(640.0, 480.0)
+        .center()
+        .focused(true)
+        .on_navigation({
+            let app = app.clone();
+            let label = label.clone();
+            move |url| {
+                if url.scheme() != "yam" {
+                    return true;
+                }
+
+                let res = match url.path() {
+                    "/open" => open_in_editor(
+                        &app,
+                        report.file.as_deref().unwrap_or(&report.path),
+                        report.line.unwrap_or(1),
+                    ),
+                    "/copy" => app
+                        .clipboard()
+                        .write_text(report.to_text())
+                        .map_err(anyhow::Error::from),
+                    _ => {
+                        if let Some(window) = app.get_webview_window(&label) {
+                            let _ = window.close();
+                        }
+                        Ok(())
+                    }
+                };
+                if let Err(err) = res {
+                    eprintln!("Failed to handle error window action: {}", err);
+                }
+
+                false
+            }
+        })
+        .build()?;
+
+    Ok(())
+}
+
+// 設定の editor の {file} と {line} を置き換えて起動する。起動できなければ既定のアプリで開く
+fn open_in_editor(app: &AppHandle, file: &Path, line: u32) -> anyhow::Result<()> {
+    let command = app
+        .state::<Mutex<Settings>>()
+        .lock()
+        .unwrap()
+        .editor
+        .clone();
+    let file = file.to_string_lossy();
+    let line = line.to_string();
+    let mut args = command
+        .split_whitespace()
+        .map(|arg| arg.replace("{file}", &file).replace("{line}", &line));
+
+    let res = match args.next() {
+        Some(program) => spawn_editor(&program, args),
+        None => Err(io::ErrorKind::NotFound.into()),
+    };
+    if let Err(err) = res {
+        eprintln!("Failed to launch editor: {}", err);
+        app.opener().open_path(file.to_string(), None::<&str>)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn spawn_editor(program: &str, args: impl Iterator<Item = String>) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // code などは .cmd なので cmd 経由で起動する
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    Command::new("cmd")
+        .arg("/C")
+        .arg(program)
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn spawn_editor(program: &str, args: impl Iterator<Item = String>) -> io::Result<()> {
+    Command::new(program).args(args).spawn()?;
+    Ok(())
+}
+
+fn render_html(report: &ErrorReport) -> String {
+    let mut details = vec![
+        ("スクリプト", report.title.clone()),
+        ("ファイル", report.path.display().to_string()),
+    ];
+    if let (Some(file), Some(line)) = (&report.file, report.line) {
+        details.push(("位置", format!("{}:{}", file.display(), line)));
+    }
+    if let Some(elapsed) = report.elapsed_text() {
+        details.push(("実行時間", elapsed));
+    }
+    let details = details
+        .iter()
+        .map(|(name, value)| format!("<dt>{}</dt><dd>{}</dd>", name, escape_html(value)))
+        .collect::<String>();
+
+    let width = report
+        .source
+        .last()
+        .map_or(0, |line| line.number.to_string().len());
+    let source = report
+        .source
+        .iter()
+        .map(|line| {
+            let text = format!("{:>width$} | {}", line.number, escape_html(&line.text));
+            if line.highlighted {
+                format!("<span class=\"highlighted\">{}</span>", text)
+            } else {
+                text
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    fill(
+        include_str!("error_window.html"),
+        &[
+            ("summary", escape_html(report.summary)),
+            ("message", escape_html(&report.message)),
+            ("details", details),
+            ("source", source),
+            (
+                "traceback",
+                report
+                    .traceback
+                    .as_deref()
+                    .map(escape_html)
+                    .unwrap_or_default(),
+            ),
+        ],
+    )
+}
+
+// トレイの「最近のエラー」
+pub struct ErrorHistory {
+    menu: Submenu<Wry>,
+    reports: Mutex<VecDeque<(MenuId, ErrorReport)>>,
+}
+impl ErrorHistory {
+    pub fn new(menu: Submenu<Wry>) -> Self {
+        ErrorHistory {
+            menu,
+            reports: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn push(&self, app: &AppHandle, report: ErrorReport) -> anyhow::Result<()> {
+        let mut label = format!("{}: {}", report.title, report.message).replace('\n', " ");
+        if let Some((index, _)) = label.char_indices().nth(LABEL_LENGTH) {
+            label.truncate(index);
+            label.push('…');
+        }
+        let item = MenuItem::new(app, label, true, None::<&str>)?;
+        self.menu.insert(&item, 0)?;
+        self.menu.set_enabled(true)?;
+
+        let mut reports = self.reports.lock().unwrap();
+        reports.push_front((item.id().clone(), report));
+        while reports.len() > HISTORY_SIZE {
+            if let Some((id, _)) = reports.pop_back() {
+                if let Some(item) = self.menu.get(&id) {
+                    self.menu.remove(&item)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+    pub fn find(&self, id: &MenuId) -> Option<ErrorReport> {
+        self.reports
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(item, _)| item == id)
+            .map(|(_, report)| report.clone())
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lua::{Manifest, Permission};
+
+// ファイルのハッシュごとに許可した権限を記録する
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Grants(HashMap<String, BTreeSet<Permission>>);
+impl Grants {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Grants::default());
+        }
+
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+    // 読めなければ何も許可していないものとして扱い、改めて確認する
+    pub fn load_or_default<P: AsRef<Path>>(path: P, on_error: impl FnOnce(anyhow::Error)) -> Self {
+        Grants::load(path).unwrap_or_else(|err| {
+            on_error(err);
+            Grants::default()
+        })
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn missing(&self, manifest: &Manifest) -> BTreeSet<Permission> {
+        match self.0.get(&manifest.hash) {
+            Some(granted) => manifest.permissions.difference(granted).copied().collect(),
+            None => manifest.permissions.clone(),
+        }
+    }
+    pub fn grant(&mut self, manifest: &Manifest) {
+        self.0
+            .entry(manifest.hash.clone())
+            .or_default()
+            .extend(manifest.permissions.iter().copied());
+    }
+}
@@ -1,25 +1,47 @@
 use std::{
     fs, io,
     path::Path,
-    sync::{Arc, Mutex},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::spawn,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use lua::LuaManager;
+use crossbeam::channel as ch;
+use error_window::ErrorHistory;
+use grants::Grants;
+use lua::{
+    ErrorReport, HostEvent, Key, LuaManager, MacroTimeline, Manifest, Output, Screen, ScriptError,
+    ScriptOptions,
+};
+use recorder::{RecordedEvent, Recorder};
+use settings::{RecordFormat, Settings};
 use tauri::{
-    menu::{CheckMenuItem, MenuBuilder},
+    menu::{CheckMenuItem, MenuBuilder, MenuItem, Submenu},
     path::BaseDirectory,
     tray::TrayIconBuilder,
-    App, AppHandle, Manager, Runtime,
+    App, AppHandle, Manager, Monitor, RunEvent, Runtime,
 };
-use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+use tauri_plugin_notification::NotificationExt;
 use tauri_plugin_opener::OpenerExt;
 
+mod error_window;
+mod grants;
 mod lua;
+mod prompt;
+mod recorder;
+mod settings;
 
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![])
         .setup(|app| {
             if let Err(err) = setup_app(app) {
@@ -34,8 +56,16 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_, event| {
+            if let RunEvent::ExitRequested {
+                code: None, api, ..
+            } = event
+            {
+                api.prevent_exit();
+            }
+        });
 }
 
 fn setup_app(app: &mut App) -> anyhow::Result<()> {
@@ -47,7 +77,60 @@ fn setup_app(app: &mut App) -> anyhow::Result<()> {
     let vscode_dir = app
         .path()
         .resolve("data/.vscode", BaseDirectory::Resource)?;
-    copy_dir_all(vscode_dir, config_dir.join(".vscode"))?;
+    copy_dir_all(&vscode_dir, config_dir.join(".vscode"))?;
+    // 起動時に読み込むファイルは、古いものが組み込み関数を上書きしないよう毎回置き換える
+    for name in ["entry.lua", "post.lua"] {
+        fs::copy(
+            vscode_dir.join("yam-docs").join(name),
+            config_dir.join(".vscode/yam-docs").join(name),
+        )?;
+    }
+    lua::write_meta(config_dir.join(".vscode/yam-docs/meta"))?;
+    lua::start_input_poller();
+
+    let settings_path = config_dir.join("settings.json");
+    let settings = Settings::load_or_default(&settings_path, |err| {
+        eprintln!(
+            "Failed to load settings: {}: {}",
+            settings_path.display(),
+            err
+        );
+        app.dialog()
+            .message(format!(
+                "設定ファイルを読み込めなかったため、既定の設定で起動します。設定を変更するとファイルは上書きされます。\n\n{}\n{}",
+                settings_path.display(),
+                err
+            ))
+            .title("設定を読み込めません")
+            .kind(tauri_plugin_dialog::MessageDialogKind::Warning)
+            .show(|_| {});
+    });
+    let stop_timeout = Duration::from_millis(settings.stop_timeout_ms);
+    let pause_key = Key::from_str(&settings.pause_key)
+        .ok()
+        .and_then(Key::keycode)
+        .ok_or_else(|| anyhow::anyhow!("Invalid pause key: {}", settings.pause_key))?;
+    let notifications = CheckMenuItem::with_id(
+        app,
+        "notifications",
+        "通知で知らせる",
+        true,
+        settings.notifications,
+        None::<&str>,
+    )?;
+    app.manage(Mutex::new(settings));
+
+    let (host, host_receiver) = ch::unbounded::<HostEvent>();
+    {
+        let app = app.handle().clone();
+        spawn(move || {
+            while let Ok(event) = host_receiver.recv() {
+                if let Err(err) = on_host_event(&app, event) {
+                    eprintln!("Failed to handle host event: {}", err);
+                }
+            }
+        });
+    }
 
     #[cfg(target_os = "macos")]
     {
@@ -69,29 +152,59 @@ fn setup_app(app: &mut App) -> anyhow::Result<()> {
             }
         })
     }) {
-        let content = fs::read_to_string(&path)?;
-        let title = content
-            .lines()
-            .next()
-            .and_then(|line| line.split_once("--[["))
-            .and_then(|(_, header)| header.split_once("]]"))
-            .map(|(header, _)| header.trim());
-        if let Some(title) = title {
-            let item = CheckMenuItem::new(app, title, true, false, None::<String>)?;
-            menu_builder = menu_builder.item(&item);
-
-            items.push((item, title.to_string(), path.to_string_lossy().to_string()));
-        } else {
-            eprintln!("Invalid file format: {}", path.display());
+        match Manifest::load(&path) {
+            Ok(Manifest { title, .. }) => {
+                let item = CheckMenuItem::new(app, &title, true, false, None::<String>)?;
+                menu_builder = menu_builder.item(&item);
+
+                items.push((item, title, path.to_string_lossy().to_string()));
+            }
+            Err(err) => eprintln!("Invalid file format: {}: {}", path.display(), err),
         }
     }
+    let pause = MenuItem::with_id(
+        app,
+        "pause-script",
+        format!("一時停止・再開 ({})", pause_key),
+        true,
+        None::<&str>,
+    )?;
+    let record = MenuItem::with_id(app, "record-macro", "マクロを記録", true, None::<&str>)?;
+    app.manage(Mutex::new(None::<Recorder>));
+    let play = MenuItem::with_id(app, "play-macro", "マクロを再生...", true, None::<&str>)?;
+    app.manage(MacroPlayback::default());
+    let error_history = Submenu::with_id(app, "error-history", "最近のエラー", false)?;
+    app.manage(ErrorHistory::new(error_history.clone()));
+
     let menu = menu_builder
         .separator()
+        .item(&pause)
+        .item(&record)
+        .item(&play)
+        .item(&error_history)
+        .item(&notifications)
         .text("open-scripts", "Scriptsフォルダを開く")
         .quit_with_text("終了")
         .build()?;
 
-    let ctx = Arc::new(Mutex::new((LuaManager::new(), Some(String::new()))));
+    let ctx = Arc::new(Mutex::new((
+        LuaManager::new(host, stop_timeout),
+        Some(String::new()),
+    )));
+    setup_failsafe(app.handle(), Arc::clone(&ctx), items.clone());
+    {
+        let app = app.handle().clone();
+        let ctx = Arc::clone(&ctx);
+        lua::on_hotkey(pause_key, move || toggle_pause(&app, &ctx));
+    }
+
+    let tray_items = TrayItems {
+        scripts: items,
+        notifications,
+        pause,
+        record,
+        play,
+    };
     let tray = TrayIconBuilder::new()
         .icon(
             app.default_window_icon()
@@ -99,15 +212,11 @@ fn setup_app(app: &mut App) -> anyhow::Result<()> {
                 .clone(),
         )
         .on_menu_event(move |app, e| {
-            if let Err(err) = on_menu_event(app, e, &items, ctx.clone()) {
-                app.dialog()
-                    .message(format!(
-                        "メニューの処理中にエラーが発生しました。\n\n{}",
-                        err
-                    ))
-                    .title("エラーが発生しました")
-                    .kind(tauri_plugin_dialog::MessageDialogKind::Error)
-                    .show(|_| {});
+            if let Err(err) = on_menu_event(app, e, &tray_items, ctx.clone()) {
+                report_error(
+                    app,
+                    format!("メニューの処理中にエラーが発生しました。\n\n{}", err),
+                );
             }
         })
         .build(app)?;
@@ -116,12 +225,208 @@ fn setup_app(app: &mut App) -> anyhow::Result<()> {
     Ok(())
 }
 
+type ScriptItems<R> = Vec<(CheckMenuItem<R>, String, String)>;
+
+fn setup_failsafe<R: Runtime>(
+    app: &AppHandle,
+    ctx: Arc<Mutex<(LuaManager, Option<String>)>>,
+    items: ScriptItems<R>,
+) {
+    let corner = app.state::<Mutex<Settings>>().lock().unwrap().failsafe;
+    let screens = match app.available_monitors() {
+        Ok(monitors) if !monitors.is_empty() => monitors.iter().map(screen).collect(),
+        Ok(_) => {
+            eprintln!("No display found, failsafe is disabled");
+            return;
+        }
+        Err(err) => {
+            eprintln!("Failed to get displays, failsafe is disabled: {}", err);
+            return;
+        }
+    };
+
+    let app = app.clone();
+    let items = Arc::new(items);
+    lua::set_failsafe(corner, screens, move || {
+        let app = app.clone();
+        let ctx = Arc::clone(&ctx);
+        let items = Arc::clone(&items);
+        // 入力の監視スレッドを止めないよう、別スレッドで止める
+        spawn(move || {
+            if let Err(err) = abort_all(&app, &ctx, &items) {
+                eprintln!("Failed to abort scripts: {}", err);
+            }
+        });
+    });
+}
+
+// マウスの座標と同じ単位にする。macOS では論理ピクセルになる
+fn screen(monitor: &Monitor) -> Screen {
+    let scale = if cfg!(target_os = "macos") {
+        monitor.scale_factor()
+    } else {
+        1.0
+    };
+    let position = monitor.position().to_logical::<f64>(scale);
+    let size = monitor.size().to_logical::<f64>(scale);
+    Screen {
+        x: position.x.round() as i32,
+        y: position.y.round() as i32,
+        width: size.width.round() as i32,
+        height: size.height.round() as i32,
+    }
+}
+
+fn toggle_pause(app: &AppHandle, ctx: &Mutex<(LuaManager, Option<String>)>) {
+    let paused = ctx.lock().unwrap().0.toggle_pause();
+    if let Some(paused) = paused {
+        if notifications_enabled(app) {
+            if paused {
+                notify(app, "一時停止", "スクリプトを一時停止しました");
+            } else {
+                notify(app, "再開", "スクリプトを再開しました");
+            }
+        }
+    }
+}
+
+// 実行中のスクリプトとマクロをすべて止める
+fn abort_all<R: Runtime>(
+    app: &AppHandle,
+    ctx: &Mutex<(LuaManager, Option<String>)>,
+    items: &ScriptItems<R>,
+) -> anyhow::Result<()> {
+    let mut stopped = false;
+    if let Some(stop_flag) = app.state::<MacroPlayback>().0.lock().unwrap().as_ref() {
+        stop_flag.store(true, Ordering::SeqCst);
+        stopped = true;
+    }
+
+    let (manager, active) = &mut *ctx.lock().unwrap();
+    if active.take().is_some_and(|active| !active.is_empty()) {
+        stopped = true;
+    }
+    manager.stop_current()?;
+    for (item, _, _) in items.iter() {
+        item.set_checked(false)?;
+    }
+
+    if stopped && notifications_enabled(app) {
+        notify(app, "緊急停止", "すべてのスクリプトを停止しました");
+    }
+
+    Ok(())
+}
+
+struct TrayItems<R: Runtime> {
+    scripts: ScriptItems<R>,
+    notifications: CheckMenuItem<R>,
+    pause: MenuItem<R>,
+    record: MenuItem<R>,
+    play: MenuItem<R>,
+}
+
+#[derive(Default)]
+struct MacroPlayback(Mutex<Option<Arc<AtomicBool>>>);
+
 fn on_menu_event(
     app: &AppHandle,
     e: tauri::menu::MenuEvent,
-    items: &[(CheckMenuItem<impl Runtime>, String, String)],
+    tray_items: &TrayItems<impl Runtime>,
     ctx: Arc<Mutex<(LuaManager, Option<String>)>>,
 ) -> anyhow::Result<()> {
+    let TrayItems {
+        scripts: items,
+        notifications,
+        pause,
+        record,
+        play,
+    } = tray_items;
+
+    if e.id() == notifications.id() {
+        let settings = app.state::<Mutex<Settings>>();
+        let mut settings = settings.lock().unwrap();
+        settings.notifications = !settings.notifications;
+        settings.save(app.path().app_config_dir()?.join("settings.json"))?;
+        notifications.set_checked(settings.notifications)?;
+
+        return Ok(());
+    }
+    if e.id() == pause.id() {
+        toggle_pause(app, &ctx);
+
+        return Ok(());
+    }
+    if let Some(report) = app.state::<ErrorHistory>().find(e.id()) {
+        error_window::show_error_report(app, report)?;
+
+        return Ok(());
+    }
+    if e.id() == record.id() {
+        let recorder = app.state::<Mutex<Option<Recorder>>>();
+        let mut recorder = recorder.lock().unwrap();
+        if let Some(recorder) = recorder.as_ref() {
+            recorder.stop();
+        } else {
+            let stop_key = app
+                .state::<Mutex<Settings>>()
+                .lock()
+                .unwrap()
+                .record_stop_key
+                .clone();
+            let stop_key = Key::from_str(&stop_key)
+                .ok()
+                .and_then(Key::keycode)
+                .ok_or_else(|| anyhow::anyhow!("Invalid stop key: {}", stop_key))?;
+
+            let app = app.clone();
+            let record = record.clone();
+            *recorder = Some(Recorder::start(stop_key, move |events| {
+                app.state::<Mutex<Option<Recorder>>>()
+                    .lock()
+                    .unwrap()
+                    .take();
+                let _ = record.set_text("マクロを記録");
+
+                if let Err(err) = save_recording(&app, &events) {
+                    report_error(
+                        &app,
+                        format!("マクロの保存中にエラーが発生しました。\n\n{}", err),
+                    );
+                }
+            }));
+            record.set_text(format!("記録を停止 ({})", stop_key))?;
+        }
+
+        return Ok(());
+    }
+    if e.id() == play.id() {
+        let playback = app.state::<MacroPlayback>();
+        if let Some(stop_flag) = playback.0.lock().unwrap().as_ref() {
+            stop_flag.store(true, Ordering::SeqCst);
+            return Ok(());
+        }
+
+        let app = app.clone();
+        let play = play.clone();
+        app.dialog()
+            .file()
+            .set_directory(app.path().app_config_dir()?)
+            .add_filter("Macro", &["json"])
+            .pick_file(move |path| {
+                let Some(path) = path else {
+                    return;
+                };
+                if let Err(err) = play_macro(&app, &play, path.to_string()) {
+                    report_error(
+                        &app,
+                        format!("マクロの再生中にエラーが発生しました。\n\n{}", err),
+                    );
+                }
+            });
+
+        return Ok(());
+    }
     if e.id.0.as_str() == "open-scripts" {
         app.opener().open_path(
             app.path().app_config_dir()?.to_string_lossy().to_string(),
@@ -144,33 +449,346 @@ fn on_menu_event(
     }
 
     if let Some(active) = active.as_ref() {
-        let (_, _, path) = items
+        let (item, title, path) = items
             .iter()
             .find(|(item, _, _)| active == &item.id().0)
             .ok_or(anyhow::anyhow!("Failed to find active item"))?;
 
+        manager.stop_current()?;
+        let manifest = Manifest::load(path)?;
+        let grants_path = app.path().app_config_dir()?.join("grants.json");
+        let mut grants = Grants::load_or_default(&grants_path, |err| {
+            eprintln!("Failed to load grants: {}: {}", grants_path.display(), err)
+        });
+        let missing = grants.missing(&manifest);
+        if missing.is_empty() {
+            start_script(app, manager, title, path, &manifest)?;
+        } else {
+            let app = app.clone();
+            let ctx = Arc::clone(&ctx);
+            let item = item.clone();
+            let title = title.clone();
+            let path = path.clone();
+            app.dialog()
+                .message(format!(
+                    "「{}」が次の権限を要求しています。\n\n{}\n\n{}許可しますか？",
+                    title,
+                    missing
+                        .iter()
+                        .map(|permission| format!("・{}", permission.name()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    if manifest.permissions_declared {
+                        ""
+                    } else {
+                        "`-- permissions:` が書かれていないため、以前と同じくすべての権限を要求しています。\n\n"
+                    }
+                ))
+                .title("権限の確認")
+                .buttons(MessageDialogButtons::OkCancelCustom(
+                    "許可する".to_string(),
+                    "キャンセル".to_string(),
+                ))
+                .show(move |ok| {
+                    let (manager, active) = &mut *ctx.lock().unwrap();
+                    // 確認中に別のスクリプトに切り替えられた
+                    if active.as_ref() != Some(&item.id().0) {
+                        return;
+                    }
+
+                    let res = if ok {
+                        grants.grant(&manifest);
+                        grants
+                            .save(&grants_path)
+                            .and_then(|()| start_script(&app, manager, &title, &path, &manifest))
+                    } else {
+                        *active = None;
+                        item.set_checked(false).map_err(anyhow::Error::from)
+                    };
+                    if let Err(err) = res {
+                        report_error(
+                            &app,
+                            format!("メニューの処理中にエラーが発生しました。\n\n{}", err),
+                        );
+                    }
+                });
+        }
+    } else {
+        manager.stop_current()?;
+    }
+
+    Ok(())
+}
+
+fn start_script(
+    app: &AppHandle,
+    manager: &mut LuaManager,
+    title: &str,
+    path: &str,
+    manifest: &Manifest,
+) -> anyhow::Result<()> {
+    let options = {
+        let settings = app.state::<Mutex<Settings>>();
+        let settings = settings.lock().unwrap();
+        ScriptOptions {
+            permissions: manifest.permissions.clone(),
+            limits: manifest.limits.or(settings.limits),
+            rate_limits: settings.rate_limits,
+            on_user_input: manifest.on_user_input.unwrap_or(settings.on_user_input),
+        }
+    };
+    let res = {
         let app = app.clone();
+        let title = title.to_string();
+        let path = path.to_string();
+        let started = Instant::now();
         manager.execute_from_file(
-            path,
+            &path,
             app.path().app_config_dir()?.join(".vscode/yam-docs"),
-            move |err| {
-                app.dialog()
-                    .message(format!(
-                        "Luaスクリプトの実行中にエラーが発生しました。\n\n{}",
-                        err,
-                    ))
-                    .title("エラーが発生しました")
-                    .kind(tauri_plugin_dialog::MessageDialogKind::Error)
-                    .show(|_| {});
+            &options,
+            move |res| match res {
+                Ok(()) => {
+                    if notifications_enabled(&app) {
+                        notify(&app, &title, "スクリプトが終了しました");
+                    }
+                }
+                Err(err) => report_script_error(&app, &title, &path, &err, Some(started.elapsed())),
             },
-        )?;
+        )
+    };
+    if let Err(err) = res {
+        return match err.downcast::<ScriptError>() {
+            Ok(err) => {
+                report_script_error(app, title, path, &err, None);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+    }
+    if notifications_enabled(app) {
+        notify(app, title, "スクリプトを開始しました");
+    }
+
+    Ok(())
+}
+
+// エラーウィンドウを開き、トレイの履歴に残す
+fn report_script_error(
+    app: &AppHandle,
+    title: &str,
+    path: &str,
+    err: &ScriptError,
+    elapsed: Option<Duration>,
+) {
+    let report = ErrorReport::new(title, path, err, elapsed);
+    if let Err(err) = app.state::<ErrorHistory>().push(app, report.clone()) {
+        eprintln!("Failed to add error history: {}", err);
+    }
+    if let Err(err) = error_window::show_error_report(app, report.clone()) {
+        eprintln!("Failed to show error window: {}", err);
+        report_error(app, report.to_text());
+    }
+}
+
+fn play_macro(app: &AppHandle, play: &MenuItem<impl Runtime>, path: String) -> anyhow::Result<()> {
+    let timeline = MacroTimeline::load(&path)?;
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    *app.state::<MacroPlayback>().0.lock().unwrap() = Some(Arc::clone(&stop_flag));
+    play.set_text("再生を停止")?;
+
+    let app = app.clone();
+    let play = play.clone();
+    spawn(move || {
+        let res = Output::new()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut output| timeline.play(&mut output, 1.0, 1, &stop_flag));
+
+        app.state::<MacroPlayback>().0.lock().unwrap().take();
+        let _ = play.set_text("マクロを再生...");
+        if let Err(err) = res {
+            report_error(
+                &app,
+                format!("マクロの再生中にエラーが発生しました。\n\n{}", err),
+            );
+        }
+    });
+
+    Ok(())
+}
+
+fn save_recording(app: &AppHandle, events: &[RecordedEvent]) -> anyhow::Result<()> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let format = app.state::<Mutex<Settings>>().lock().unwrap().record_format;
+    let config_dir = app.path().app_config_dir()?;
+    let path = match format {
+        RecordFormat::Lua => {
+            let path = config_dir.join(format!("macro-{}.lua", secs));
+            fs::write(
+                &path,
+                recorder::to_lua_script(
+                    &format!(
+                        "記録したマクロ {}",
+                        recorder::format_timestamp(SystemTime::now())
+                    ),
+                    events,
+                ),
+            )?;
+            path
+        }
+        RecordFormat::Json => {
+            let path = config_dir.join(format!("macro-{}.json", secs));
+            fs::write(
+                &path,
+                serde_json::to_string_pretty(&recorder::to_macro_file(events))?,
+            )?;
+            path
+        }
+    };
+
+    let message = format!("マクロを保存しました。\n\n{}", path.display());
+    if notifications_enabled(app) {
+        notify(app, "マクロを記録しました", &message);
     } else {
-        manager.stop_current()?;
+        app.dialog()
+            .message(message)
+            .title("マクロを記録しました")
+            .show(|_| {});
+    }
+
+    Ok(())
+}
+
+fn on_host_event(app: &AppHandle, event: HostEvent) -> anyhow::Result<()> {
+    match event {
+        HostEvent::ScriptStuck { path, timeout } => {
+            app.dialog()
+                .message(format!(
+                    "{} が停止の要求から{}秒経っても終了しませんでした。\n\nスクリプトを切り離し、押されていたキーを離しました。処理が終わるまでバックグラウンドに残る場合があります。",
+                    path.display(),
+                    timeout.as_secs_f64()
+                ))
+                .title("スクリプトが応答しません")
+                .kind(tauri_plugin_dialog::MessageDialogKind::Warning)
+                .show(|_| {});
+        }
+        HostEvent::Notify { title, body, icon } => {
+            let mut builder = app.notification().builder().title(title).body(body);
+            if let Some(icon) = icon {
+                builder = builder.icon(icon);
+            }
+            builder.show()?;
+        }
+        HostEvent::Alert {
+            title,
+            message,
+            res,
+        } => {
+            let mut builder = app.dialog().message(message);
+            if let Some(title) = title {
+                builder = builder.title(title);
+            }
+            builder.show(move |ok| {
+                let _ = res.send(ok);
+            });
+        }
+        HostEvent::Confirm {
+            title,
+            message,
+            res,
+        } => {
+            let mut builder = app
+                .dialog()
+                .message(message)
+                .buttons(MessageDialogButtons::OkCancel);
+            if let Some(title) = title {
+                builder = builder.title(title);
+            }
+            builder.show(move |ok| {
+                let _ = res.send(ok);
+            });
+        }
+        HostEvent::Prompt {
+            title,
+            message,
+            default,
+            res,
+        } => {
+            prompt::show_prompt(
+                app,
+                title.as_deref().unwrap_or(&app.package_info().name),
+                &message,
+                &default,
+                move |value| {
+                    let _ = res.send(value);
+                },
+            )?;
+        }
+        HostEvent::PickFile {
+            title,
+            directory,
+            filters,
+            res,
+        } => {
+            let mut builder = app.dialog().file();
+            if let Some(title) = title {
+                builder = builder.set_title(title);
+            }
+            if let Some(directory) = directory {
+                builder = builder.set_directory(directory);
+            }
+            for (name, extensions) in filters {
+                builder = builder.add_filter(
+                    name,
+                    &extensions.iter().map(String::as_str).collect::<Vec<_>>(),
+                );
+            }
+            builder.pick_file(move |path| {
+                let _ = res.send(path.map(|path| path.to_string()));
+            });
+        }
+        HostEvent::PickFolder {
+            title,
+            directory,
+            res,
+        } => {
+            let mut builder = app.dialog().file();
+            if let Some(title) = title {
+                builder = builder.set_title(title);
+            }
+            if let Some(directory) = directory {
+                builder = builder.set_directory(directory);
+            }
+            builder.pick_folder(move |path| {
+                let _ = res.send(path.map(|path| path.to_string()));
+            });
+        }
     }
 
     Ok(())
 }
 
+fn notifications_enabled(app: &AppHandle) -> bool {
+    app.state::<Mutex<Settings>>().lock().unwrap().notifications
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    if let Err(err) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("Failed to show notification: {}", err);
+    }
+}
+
+fn report_error(app: &AppHandle, message: String) {
+    if notifications_enabled(app) {
+        notify(app, "エラーが発生しました", &message);
+    } else {
+        app.dialog()
+            .message(message)
+            .title("エラーが発生しました")
+            .kind(tauri_plugin_dialog::MessageDialogKind::Error)
+            .show(|_| {});
+    }
+}
+
 fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
     fs::create_dir_all(&dst)?;
     for entry in fs::read_dir(&src)? {
@@ -0,0 +1,896 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use strum::{IntoEnumIterator, VariantNames};
+
+use super::model::{Button, Coordinate, Curve, Direction, Key, QUERY_ALIASES};
+
+pub struct MetaFile {
+    pub name: &'static str,
+    pub sections: &'static [Section],
+}
+
+pub enum Section {
+    Module {
+        name: &'static str,
+        doc: &'static str,
+    },
+    Table {
+        name: &'static str,
+    },
+    Class {
+        name: &'static str,
+        fields: &'static [Param],
+    },
+    Function {
+        name: &'static str,
+        doc: &'static str,
+        params: &'static [Param],
+        returns: &'static [Param],
+    },
+    Enum {
+        name: &'static str,
+        values: EnumValues,
+    },
+}
+
+pub enum EnumValues {
+    Names(&'static [&'static str]),
+    Dynamic(fn() -> Vec<String>),
+}
+
+pub struct Param {
+    pub name: &'static str,
+    pub ty: &'static str,
+    pub doc: &'static str,
+}
+
+const fn p(name: &'static str, ty: &'static str, doc: &'static str) -> Param {
+    Param { name, ty, doc }
+}
+
+fn query_names() -> Vec<String> {
+    let mut names: Vec<String> = Key::iter()
+        .filter(|key| key.keycode().is_some())
+        .map(|key| key.name().to_string())
+        .collect();
+    // 以前の`Key.Query`の名前。OS ごとに生成ファイルが変わらないよう固定の表から作る
+    names.extend(QUERY_ALIASES.iter().map(|(name, _)| name.to_string()));
+    names
+}
+
+// 番号は OS によらず同じ
+const BUTTON_DOC: &str =
+    "ボタンの名前または番号 (1: Left, 2: Right, 3: Middle, 4: Back, 5: Forward)";
+
+pub static META: &[MetaFile] = &[
+    MetaFile {
+        name: "keyboard",
+        sections: &[
+            Section::Module {
+                name: "keyboard",
+                doc: "キーボード関連の関数を提供するモジュール",
+            },
+            Section::Function {
+                name: "keyboard.press",
+                doc: "指定されたキーを押す",
+                params: &[p("key", "Key.Send", "キー")],
+                returns: &[],
+            },
+            Section::Function {
+                name: "keyboard.release",
+                doc: "指定されたキーを離す",
+                params: &[p("key", "Key.Send", "キー")],
+                returns: &[],
+            },
+            Section::Function {
+                name: "keyboard.click",
+                doc: "指定されたキーを押して、離す",
+                params: &[
+                    p("key", "Key.Send", "キー"),
+                    p("opts?", "ClickOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+            Section::Function {
+                name: "keyboard.is_pressing",
+                doc: "指定されたキーが押されているかどうかを取得する",
+                params: &[p("key", "Key.Query", "キー")],
+                returns: &[p("pressed", "boolean", "押されているかどうか")],
+            },
+            Section::Function {
+                name: "keyboard.just_pressed",
+                doc: "前回この場所で呼び出してからキーが押されたかどうかを取得する\n呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない",
+                params: &[
+                    p("key", "Key.Query", "キー"),
+                    p("id?", "string", "呼び出し元の代わりに使う識別子"),
+                ],
+                returns: &[p("pressed", "boolean", "押されたかどうか")],
+            },
+            Section::Function {
+                name: "keyboard.just_released",
+                doc: "前回この場所で呼び出してからキーが離されたかどうかを取得する\n呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない",
+                params: &[
+                    p("key", "Key.Query", "キー"),
+                    p("id?", "string", "呼び出し元の代わりに使う識別子"),
+                ],
+                returns: &[p("released", "boolean", "離されたかどうか")],
+            },
+            Section::Function {
+                name: "keyboard.was_pressed_since",
+                doc: "`input.token`を取得してからキーが押されたかどうかを取得する",
+                params: &[
+                    p("key", "Key.Query", "キー"),
+                    p("token", "integer", "`input.token`の戻り値"),
+                ],
+                returns: &[p("pressed", "boolean", "押されたかどうか")],
+            },
+            Section::Function {
+                name: "keyboard.pressed",
+                doc: "現在押されているすべてのキーを取得する",
+                params: &[],
+                returns: &[p("keys", "Key.Query[]", "押されているキー")],
+            },
+            Section::Class {
+                name: "LockState",
+                fields: &[
+                    p("caps_lock", "boolean", "Caps Lockが有効かどうか"),
+                    p("num_lock", "boolean", "Num Lockが有効かどうか(macOSでは常にfalse)"),
+                ],
+            },
+            Section::Function {
+                name: "keyboard.lock_state",
+                doc: "Caps LockとNum Lockの状態を取得する",
+                params: &[],
+                returns: &[p("state", "LockState", "ロックキーの状態")],
+            },
+            Section::Function {
+                name: "keyboard.combo",
+                doc: "キーの組み合わせを押す\n\
+                      左から順に押し、逆順に離す\n\
+                      \n\
+                      例: `keyboard.combo(\"LCtrl+LShift+s\")`",
+                params: &[p("spec", "string", "`+`区切りのキー名または文字")],
+                returns: &[],
+            },
+            Section::Function {
+                name: "keyboard.send",
+                doc: "AutoHotkey風の書式でキー入力を送る\n\
+                      \n\
+                      - `{Enter}` キーを押して離す\n\
+                      - `{LCtrl down}` / `{LCtrl up}` キーを押す / 離す\n\
+                      - `{Tab 3}` キーを3回押す\n\
+                      - `{{}` / `{}}` 波括弧そのもの\n\
+                      - それ以外の文字はそのまま入力される\n\
+                      \n\
+                      例: `keyboard.send(\"{LCtrl down}c{LCtrl up}{Enter}\")`",
+                params: &[p("spec", "string", "送信する文字列")],
+                returns: &[],
+            },
+            Section::Class {
+                name: "TypeOptions",
+                fields: &[
+                    p(
+                        "delay_ms?",
+                        "integer",
+                        "1文字ごとの待機時間 (ミリ秒, デフォルト: 0)",
+                    ),
+                    p(
+                        "jitter?",
+                        "integer",
+                        "待機時間に加えるランダムな揺らぎ (±ミリ秒, デフォルト: 0)",
+                    ),
+                ],
+            },
+            Section::Function {
+                name: "keyboard.type",
+                doc: "文字列を入力する\n改行はEnter、タブはTabとして入力される",
+                params: &[
+                    p("text", "string", "入力する文字列"),
+                    p("opts?", "TypeOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+            Section::Module {
+                name: "keyboard.char",
+                doc: "Enumにないキー用の関数",
+            },
+            Section::Function {
+                name: "keyboard.char.press",
+                doc: "指定された文字を押す",
+                params: &[p("char", "string", "文字")],
+                returns: &[],
+            },
+            Section::Function {
+                name: "keyboard.char.release",
+                doc: "指定された文字を離す",
+                params: &[p("char", "string", "文字")],
+                returns: &[],
+            },
+            Section::Function {
+                name: "keyboard.char.click",
+                doc: "指定された文字を押して、離す",
+                params: &[p("char", "string", "文字")],
+                returns: &[],
+            },
+        ],
+    },
+    MetaFile {
+        name: "mouse",
+        sections: &[
+            Section::Module {
+                name: "mouse",
+                doc: "マウス関連の関数を提供するモジュール",
+            },
+            Section::Function {
+                name: "mouse.get_pos",
+                doc: "マウスの位置を取得する",
+                params: &[],
+                returns: &[p("x", "number", "x座標"), p("y", "number", "y座標")],
+            },
+            Section::Function {
+                name: "mouse.move",
+                doc: "マウスを指定した位置に移動する",
+                params: &[
+                    p("x", "number", "x座標"),
+                    p("y", "number", "y座標"),
+                    p("coord", "Coord", "座標系"),
+                ],
+                returns: &[],
+            },
+            Section::Class {
+                name: "MoveSmoothOptions",
+                fields: &[
+                    p(
+                        "duration?",
+                        "integer",
+                        "移動にかける時間(ミリ秒、デフォルト: 300)",
+                    ),
+                    p("curve?", "Curve", "移動の曲線(デフォルト: \"EaseInOut\")"),
+                    p("coord?", "Coord", "座標系(デフォルト: \"Abs\")"),
+                ],
+            },
+            Section::Function {
+                name: "mouse.move_smooth",
+                doc: "マウスを指定した位置まで滑らかに移動する",
+                params: &[
+                    p("x", "number", "x座標"),
+                    p("y", "number", "y座標"),
+                    p("opts?", "MoveSmoothOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+            Section::Class {
+                name: "ScrollOptions",
+                fields: &[p(
+                    "duration?",
+                    "integer",
+                    "スクロールにかける時間(ミリ秒、デフォルト: 0で一度にスクロール)",
+                )],
+            },
+            Section::Function {
+                name: "mouse.scroll",
+                doc: "マウスホイールをスクロールする\n正の値で右/下、負の値で左/上にスクロールする",
+                params: &[
+                    p("dx", "integer", "横方向のスクロール量(ノッチ数)"),
+                    p("dy", "integer", "縦方向のスクロール量(ノッチ数)"),
+                    p("opts?", "ScrollOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.press",
+                doc: "マウスのボタンを押す",
+                params: &[p("button", "Button.Send | integer", BUTTON_DOC)],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.release",
+                doc: "マウスのボタンを離す",
+                params: &[p("button", "Button.Send | integer", BUTTON_DOC)],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.click",
+                doc: "マウスのボタンを押して、離す",
+                params: &[
+                    p("button", "Button.Send | integer", BUTTON_DOC),
+                    p("opts?", "ClickOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.click_at",
+                doc: "指定した位置に移動してクリックする",
+                params: &[
+                    p("x", "number", "x座標"),
+                    p("y", "number", "y座標"),
+                    p(
+                        "button?",
+                        "Button.Send | integer",
+                        "ボタン(デフォルト: \"Left\")",
+                    ),
+                ],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.double_click",
+                doc: "ダブルクリックする",
+                params: &[p(
+                    "button?",
+                    "Button.Send | integer",
+                    "ボタン(デフォルト: \"Left\")",
+                )],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.multi_click",
+                doc: "指定した回数だけ連続でクリックする",
+                params: &[
+                    p("count", "integer", "クリックする回数"),
+                    p("interval?", "integer", "クリックの間隔(ミリ秒、デフォルト: 50)"),
+                    p(
+                        "button?",
+                        "Button.Send | integer",
+                        "ボタン(デフォルト: \"Left\")",
+                    ),
+                ],
+                returns: &[],
+            },
+            Section::Class {
+                name: "Point",
+                fields: &[p("x", "number", "x座標"), p("y", "number", "y座標")],
+            },
+            Section::Class {
+                name: "DragOptions",
+                fields: &[
+                    p(
+                        "button?",
+                        "Button.Send | integer",
+                        "ボタン(デフォルト: \"Left\")",
+                    ),
+                    p(
+                        "duration?",
+                        "integer",
+                        "移動にかける時間(ミリ秒、デフォルト: 300)",
+                    ),
+                ],
+            },
+            Section::Function {
+                name: "mouse.drag",
+                doc: "ボタンを押したまま`from`から`to`まで移動する\n途中でスクリプトが停止されてもボタンは離される",
+                params: &[
+                    p("from", "Point | number[]", "開始位置(絶対座標)"),
+                    p("to", "Point | number[]", "終了位置(絶対座標)"),
+                    p("opts?", "DragOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+            Section::Function {
+                name: "mouse.is_pressing",
+                doc: "マウスのボタンが押されているかどうかを取得する",
+                params: &[p("button", "Button.Query | integer", BUTTON_DOC)],
+                returns: &[p("pressed", "boolean", "押されているかどうか")],
+            },
+            Section::Function {
+                name: "mouse.just_pressed",
+                doc: "前回この場所で呼び出してからボタンが押されたかどうかを取得する\n呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない",
+                params: &[
+                    p("button", "Button.Query | integer", BUTTON_DOC),
+                    p("id?", "string", "呼び出し元の代わりに使う識別子"),
+                ],
+                returns: &[p("pressed", "boolean", "押されたかどうか")],
+            },
+            Section::Function {
+                name: "mouse.just_released",
+                doc: "前回この場所で呼び出してからボタンが離されたかどうかを取得する\n呼び出し元ごとに記録されるので、別の場所での呼び出しに影響されない",
+                params: &[
+                    p("button", "Button.Query | integer", BUTTON_DOC),
+                    p("id?", "string", "呼び出し元の代わりに使う識別子"),
+                ],
+                returns: &[p("released", "boolean", "離されたかどうか")],
+            },
+            Section::Function {
+                name: "mouse.was_pressed_since",
+                doc: "`input.token`を取得してからボタンが押されたかどうかを取得する",
+                params: &[
+                    p("button", "Button.Query | integer", BUTTON_DOC),
+                    p("token", "integer", "`input.token`の戻り値"),
+                ],
+                returns: &[p("pressed", "boolean", "押されたかどうか")],
+            },
+            Section::Class {
+                name: "MouseState",
+                fields: &[
+                    p("x", "number", "x座標"),
+                    p("y", "number", "y座標"),
+                    p(
+                        "buttons",
+                        "table<Button.Query, boolean>",
+                        "各ボタンが押されているかどうか",
+                    ),
+                ],
+            },
+            Section::Function {
+                name: "mouse.state",
+                doc: "マウスの位置とすべてのボタンの状態を取得する",
+                params: &[],
+                returns: &[p("state", "MouseState", "マウスの状態")],
+            },
+        ],
+    },
+    MetaFile {
+        name: "ui",
+        sections: &[
+            Section::Module {
+                name: "ui",
+                doc: "ダイアログ関連の関数を提供するモジュール\n\
+                      いずれの関数も応答があるまでスクリプトを停止し、その間にスクリプトが停止された場合は`nil`を返す",
+            },
+            Section::Class {
+                name: "FileFilter",
+                fields: &[
+                    p("name", "string", "フィルタの名前"),
+                    p("extensions", "string[]", "拡張子の一覧"),
+                ],
+            },
+            Section::Class {
+                name: "PickOptions",
+                fields: &[
+                    p("title?", "string", "タイトル"),
+                    p("directory?", "string", "初期ディレクトリ"),
+                    p(
+                        "filters?",
+                        "FileFilter[]",
+                        "ファイルのフィルタ(`ui.pick_file`のみ)",
+                    ),
+                ],
+            },
+            Section::Function {
+                name: "ui.alert",
+                doc: "メッセージを表示する",
+                params: &[
+                    p("message", "string", "メッセージ"),
+                    p("title?", "string", "タイトル"),
+                ],
+                returns: &[p("closed", "boolean?", "閉じられたかどうか")],
+            },
+            Section::Function {
+                name: "ui.confirm",
+                doc: "OK/キャンセルの確認ダイアログを表示する",
+                params: &[
+                    p("message", "string", "メッセージ"),
+                    p("title?", "string", "タイトル"),
+                ],
+                returns: &[p("ok", "boolean?", "OKが押されたかどうか")],
+            },
+            Section::Function {
+                name: "ui.prompt",
+                doc: "文字列の入力ダイアログを表示する",
+                params: &[
+                    p("message", "string", "メッセージ"),
+                    p("default?", "string", "初期値"),
+                    p("title?", "string", "タイトル"),
+                ],
+                returns: &[p(
+                    "value",
+                    "string?",
+                    "入力された文字列(キャンセルされた場合は`nil`)",
+                )],
+            },
+            Section::Function {
+                name: "ui.pick_file",
+                doc: "ファイルの選択ダイアログを表示する",
+                params: &[p("opts?", "PickOptions", "オプション")],
+                returns: &[p("path", "string?", "選択されたファイルのパス")],
+            },
+            Section::Function {
+                name: "ui.pick_folder",
+                doc: "フォルダの選択ダイアログを表示する",
+                params: &[p("opts?", "PickOptions", "オプション")],
+                returns: &[p("path", "string?", "選択されたフォルダのパス")],
+            },
+        ],
+    },
+    MetaFile {
+        name: "macro",
+        sections: &[
+            Section::Module {
+                name: "macro",
+                doc: "記録したマクロ(JSON)を再生するモジュール",
+            },
+            Section::Class {
+                name: "MacroPlayOptions",
+                fields: &[
+                    p("speed?", "number", "再生速度の倍率(デフォルト: 1)"),
+                    p(
+                        "loops?",
+                        "integer",
+                        "繰り返す回数(0で停止されるまで繰り返す、デフォルト: 1)",
+                    ),
+                ],
+            },
+            Section::Function {
+                name: "macro.play",
+                doc: "マクロファイルを再生する\n相対パスはスクリプトのあるフォルダから解決される",
+                params: &[
+                    p("path", "string", "マクロファイルのパス"),
+                    p("opts?", "MacroPlayOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+        ],
+    },
+    MetaFile {
+        name: "enum",
+        sections: &[
+            Section::Enum {
+                name: "Direction",
+                values: EnumValues::Names(Direction::VARIANTS),
+            },
+            Section::Table { name: "Key" },
+            Section::Enum {
+                name: "Key.Send",
+                values: EnumValues::Names(Key::VARIANTS),
+            },
+            Section::Enum {
+                name: "Key.Query",
+                values: EnumValues::Dynamic(query_names),
+            },
+            Section::Table { name: "Button" },
+            Section::Enum {
+                name: "Button.Send",
+                values: EnumValues::Names(Button::VARIANTS),
+            },
+            Section::Enum {
+                name: "Button.Query",
+                values: EnumValues::Names(&["Left", "Right", "Middle", "Back", "Forward"]),
+            },
+            Section::Enum {
+                name: "Coord",
+                values: EnumValues::Names(Coordinate::VARIANTS),
+            },
+            Section::Enum {
+                name: "Curve",
+                values: EnumValues::Names(Curve::VARIANTS),
+            },
+        ],
+    },
+    MetaFile {
+        name: "utils",
+        sections: &[
+            Section::Function {
+                name: "sleep",
+                doc: "指定されたミリ秒数だけ待機する",
+                params: &[
+                    p("ms", "number", "待機するミリ秒数"),
+                    p(
+                        "jitter?",
+                        "number",
+                        "待機時間に加えるランダムな揺らぎ(±ミリ秒)",
+                    ),
+                ],
+                returns: &[],
+            },
+            Section::Function {
+                name: "sleep_precise",
+                doc: "指定されたミリ秒数だけ正確に待機する\n最後の数ミリ秒はCPUを使って待つため、`sleep`より負荷が高い",
+                params: &[p("ms", "number", "待機するミリ秒数(小数も指定できる)")],
+                returns: &[],
+            },
+            Section::Function {
+                name: "every",
+                doc: "関数を一定の間隔で繰り返し呼ぶ\n関数の処理時間を差し引いて次の呼び出しまで待つため、呼び出しの間隔はずれない\n処理が間隔より長くかかった場合は、遅れた回を飛ばして次の間隔に合わせる\n関数が`false`を返すと終了する",
+                params: &[
+                    p("hz", "number", "1秒あたりの呼び出し回数"),
+                    p("f", "fun(tick: integer): boolean?", "呼び出す関数(`tick`は1から数えた回数)"),
+                ],
+                returns: &[],
+            },
+            Section::Module {
+                name: "time",
+                doc: "時間の計測に関する関数を提供するモジュール\n時刻はスクリプトの開始からのミリ秒数で、一時停止していた時間は含まない",
+            },
+            Section::Function {
+                name: "time.now",
+                doc: "現在の時刻を取得する\nミリ秒未満の精度があり、時計の変更の影響を受けない",
+                params: &[],
+                returns: &[p("ms", "number", "スクリプトの開始からの経過時間(ミリ秒)")],
+            },
+            Section::Function {
+                name: "time.since",
+                doc: "指定された時刻からの経過時間を取得する",
+                params: &[p("t", "number", "`time.now`で取得した時刻")],
+                returns: &[p("ms", "number", "経過時間(ミリ秒)")],
+            },
+            Section::Class {
+                name: "HumanizeOptions",
+                fields: &[
+                    p(
+                        "delay?",
+                        "integer",
+                        "押している時間に加えるランダムな揺らぎ(ミリ秒、デフォルト: 40)",
+                    ),
+                    p(
+                        "position?",
+                        "integer",
+                        "クリック位置に加えるランダムな揺らぎ(ピクセル、デフォルト: 2)",
+                    ),
+                ],
+            },
+            Section::Class {
+                name: "ClickOptions",
+                fields: &[p(
+                    "humanize?",
+                    "boolean | HumanizeOptions",
+                    "人間らしい揺らぎを加えるかどうか",
+                )],
+            },
+            Section::Module {
+                name: "humanize",
+                doc: "ランダムな揺らぎに関する関数を提供するモジュール",
+            },
+            Section::Function {
+                name: "humanize.seed",
+                doc: "揺らぎに使う乱数のシードを設定する\n同じシードを設定すると同じ揺らぎが再現される",
+                params: &[p("seed", "integer", "シード")],
+                returns: &[],
+            },
+            Section::Module {
+                name: "input",
+                doc: "ユーザーの入力に関する関数を提供するモジュール",
+            },
+            Section::Function {
+                name: "input.idle_ms",
+                doc: "ユーザーが最後にキーボードやマウスを操作してからの時間を取得する",
+                params: &[],
+                returns: &[p("ms", "integer", "経過時間(ミリ秒)")],
+            },
+            Section::Function {
+                name: "input.token",
+                doc: "現在の時点を表すトークンを取得する\n`keyboard.was_pressed_since`などに渡して、それ以降に押されたかどうかを調べる",
+                params: &[],
+                returns: &[p("token", "integer", "トークン")],
+            },
+            Section::Class {
+                name: "NotifyOptions",
+                fields: &[p("icon?", "string", "アイコンのパス")],
+            },
+            Section::Function {
+                name: "notify",
+                doc: "デスクトップ通知を表示する",
+                params: &[
+                    p("title", "string", "タイトル"),
+                    p("body?", "string", "本文"),
+                    p("opts?", "NotifyOptions", "オプション"),
+                ],
+                returns: &[],
+            },
+        ],
+    },
+];
+
+impl MetaFile {
+    pub fn render(&self) -> String {
+        let mut out = String::from("---@meta\n");
+        for section in self.sections {
+            out.push('\n');
+            section.render(&mut out);
+        }
+        out
+    }
+}
+
+impl Section {
+    fn render(&self, out: &mut String) {
+        match self {
+            Section::Module { name, doc } => {
+                writeln!(out, "--===== {} =====--", name).unwrap();
+                write_doc(out, doc);
+                if !name.contains('.') {
+                    writeln!(out, "---@class {}", name).unwrap();
+                }
+                writeln!(out, "{} = {{}}", name).unwrap();
+            }
+            Section::Table { name } => {
+                writeln!(out, "{} = {{}}", name).unwrap();
+            }
+            Section::Class { name, fields } => {
+                writeln!(out, "---@class {}", name).unwrap();
+                for field in *fields {
+                    writeln!(out, "---@field {} {} {}", field.name, field.ty, field.doc).unwrap();
+                }
+            }
+            Section::Function {
+                name,
+                doc,
+                params,
+                returns,
+            } => {
+                write_doc(out, doc);
+                for param in *params {
+                    writeln!(out, "---@param {} {} {}", param.name, param.ty, param.doc).unwrap();
+                }
+                for ret in *returns {
+                    writeln!(out, "---@return {} {} {}", ret.ty, ret.name, ret.doc).unwrap();
+                }
+                let args: Vec<_> = params
+                    .iter()
+                    .map(|param| param.name.trim_end_matches('?'))
+                    .collect();
+                writeln!(out, "function {}({}) end", name, args.join(", ")).unwrap();
+            }
+            Section::Enum { name, values } => {
+                writeln!(out, "---@enum {}", name).unwrap();
+                writeln!(out, "{} = {{", name).unwrap();
+                match values {
+                    EnumValues::Names(names) => {
+                        for name in *names {
+                            writeln!(out, "    {} = \"{}\",", name, name).unwrap();
+                        }
+                    }
+                    EnumValues::Dynamic(names) => {
+                        for name in names() {
+                            writeln!(out, "    {} = \"{}\",", name, name).unwrap();
+                        }
+                    }
+                }
+                writeln!(out, "}}").unwrap();
+            }
+        }
+    }
+}
+
+fn write_doc(out: &mut String, doc: &str) {
+    for line in doc.lines() {
+        writeln!(out, "---{}", line).unwrap();
+    }
+}
+
+pub fn write_meta<P: AsRef<Path>>(dir: P) -> io::Result<()> {
+    fs::create_dir_all(&dir)?;
+    for file in META {
+        fs::write(
+            dir.as_ref().join(format!("{}.lua", file.name)),
+            file.render(),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        env, fs,
+        sync::{atomic::AtomicBool, Arc, Mutex},
+    };
+
+    use crossbeam::channel as ch;
+    use mlua::{Lua, LuaOptions, Table, Value};
+    use rand::{rngs::StdRng, SeedableRng};
+    use strum::IntoEnumIterator;
+
+    use super::{Section, META};
+    use crate::lua::instance::{register_builtins, register_timing, std_libs};
+    use crate::lua::limits::{Budget, Limits};
+    use crate::lua::manifest::Permission;
+    use crate::lua::pause::PauseState;
+    use crate::lua::throttle::RateLimits;
+    use crate::lua::worker::{Worker, WorkerContext};
+
+    const DOCS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/.vscode/yam-docs");
+
+    // `UPDATE_META=1 cargo test` で data/ 以下を再生成する
+    #[test]
+    fn meta_files_are_up_to_date() {
+        for file in META {
+            let path = format!("{}/meta/{}.lua", DOCS_DIR, file.name);
+            if env::var_os("UPDATE_META").is_some() {
+                fs::write(&path, file.render()).unwrap();
+                continue;
+            }
+            assert!(
+                fs::read_to_string(&path).unwrap() == file.render(),
+                "{} is out of date, run `UPDATE_META=1 cargo test`",
+                path
+            );
+        }
+    }
+
+    fn runtime(permissions: &BTreeSet<Permission>) -> Lua {
+        let lua = Lua::new_with(std_libs(permissions), LuaOptions::default()).unwrap();
+        let (host, _host_receiver) = ch::unbounded();
+        let exit_flag = Arc::new(AtomicBool::new(false));
+        let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(0)));
+        let pause = Arc::new(PauseState::default());
+        // 関数を呼ばなければワーカーは起動しない
+        let worker = Worker::new(WorkerContext {
+            rate_limits: RateLimits::default(),
+            exit_flag: Arc::clone(&exit_flag),
+            abandoned: Arc::new(AtomicBool::new(false)),
+            budget: Arc::new(Budget::new(Limits::default(), Arc::clone(&pause))),
+            pause: Arc::clone(&pause),
+            host: host.clone(),
+            rng: Arc::clone(&rng),
+        });
+        register_builtins(
+            &lua,
+            permissions,
+            Arc::new(worker),
+            DOCS_DIR,
+            DOCS_DIR.as_ref(),
+        )
+        .unwrap();
+        register_timing(&lua, rng, exit_flag, pause).unwrap();
+        lua
+    }
+
+    #[test]
+    fn documented_functions_match_runtime() {
+        let lua = runtime(&Permission::iter().collect());
+        let post = fs::read_to_string(format!("{}/post.lua", DOCS_DIR)).unwrap();
+        let mut documented = Vec::new();
+        let mut modules = Vec::new();
+        for file in META {
+            for section in file.sections {
+                match section {
+                    Section::Function { name, .. } => documented.push(name.to_string()),
+                    Section::Module { name, .. } => modules.push(*name),
+                    _ => {}
+                }
+            }
+        }
+
+        let lookup = |path: &str| {
+            path.split('.')
+                .try_fold(Value::Table(lua.globals()), |value, key| match value {
+                    Value::Table(table) => table.get::<Value>(key).ok(),
+                    _ => None,
+                })
+                .unwrap_or(Value::Nil)
+        };
+        for name in &documented {
+            assert!(
+                lookup(name).is_function(),
+                "{} is documented but not registered",
+                name
+            );
+        }
+        for module in modules {
+            let table: Table = lua.load(module).eval().unwrap();
+            for pair in table.pairs::<String, Value>() {
+                let (key, value) = pair.unwrap();
+                let name = format!("{}.{}", module, key);
+                if value.is_function() && !post.contains(&format!("function {}(", name)) {
+                    assert!(
+                        documented.contains(&name),
+                        "{} is registered but not documented",
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ungranted_builtins_are_denied() {
+        let lua = runtime(&BTreeSet::from([Permission::Input]));
+        for source in ["notify('title')", "ui.pick_file()", "require('meta.enum')"] {
+            let err = lua.load(source).exec().unwrap_err();
+            assert!(
+                err.to_string().contains("permission"),
+                "{}: {}",
+                source,
+                err
+            );
+        }
+        assert!(lua.load("package").eval::<Value>().unwrap().is_nil());
+        // 組み込みの定義は読み込まれている
+        assert!(lua.load("Key.Send.A").eval::<String>().is_ok());
+
+        // 許可されていなければ作られない
+        let lua = runtime(&BTreeSet::new());
+        assert!(lua.load("keyboard.is_down").eval::<Value>().is_err());
+        assert!(lua.load("ui.alert").eval::<Value>().unwrap().is_function());
+    }
+}
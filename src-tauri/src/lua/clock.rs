@@ -0,0 +1,224 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{sleep, yield_now},
+    time::{Duration, Instant},
+};
+
+use super::pause::PauseState;
+
+// 一度に眠る最大の時間。一時停止や停止に気づくまでの遅れになる
+const SLEEP_CHUNK: Duration = Duration::from_millis(10);
+// 残りがこれより短くなったらスピンで待つ。実際に眠りすぎた量に合わせて調整する
+const INITIAL_MARGIN: Duration = Duration::from_millis(2);
+const MIN_MARGIN: Duration = Duration::from_millis(1);
+const MAX_MARGIN: Duration = Duration::from_millis(20);
+
+// スクリプトの開始からの経過時間。一時停止していた時間は含まない
+pub struct Clock {
+    origin: Instant,
+    pause: Arc<PauseState>,
+    margin: Mutex<Duration>,
+}
+impl Clock {
+    pub fn new(pause: Arc<PauseState>) -> Self {
+        Clock {
+            origin: Instant::now(),
+            pause,
+            margin: Mutex::new(INITIAL_MARGIN),
+        }
+    }
+
+    pub fn now(&self) -> Duration {
+        // 一時停止中に進まないよう、同じ時刻で計算する
+        let now = Instant::now();
+        (now - self.origin).saturating_sub(self.pause.total_at(now))
+    }
+
+    // 眠れるところまで眠り、最後はスピンで待つ。stop_flag が立ったら false
+    pub fn sleep_until(&self, target: Duration, stop_flag: &AtomicBool) -> bool {
+        loop {
+            if stop_flag.load(Ordering::SeqCst) {
+                return false;
+            }
+            if self.pause.is_paused() && !self.pause.wait(stop_flag) {
+                return false;
+            }
+
+            let now = self.now();
+            if now >= target {
+                return true;
+            }
+            let remaining = target - now;
+            let margin = *self.margin.lock().unwrap();
+            if remaining > margin {
+                let requested = (remaining - margin).min(SLEEP_CHUNK);
+                let start = Instant::now();
+                sleep(requested);
+                self.record(start.elapsed().saturating_sub(requested));
+            } else {
+                yield_now();
+            }
+        }
+    }
+    pub fn sleep(&self, duration: Duration, stop_flag: &AtomicBool) -> bool {
+        self.sleep_until(self.now() + duration, stop_flag)
+    }
+
+    // 遅れたときはすぐに広げ、遅れなければ少しずつ狭める
+    fn record(&self, late: Duration) {
+        let mut margin = self.margin.lock().unwrap();
+        *margin = (late * 2)
+            .max(*margin * 7 / 8)
+            .clamp(MIN_MARGIN, MAX_MARGIN);
+    }
+}
+
+// スクリプトから渡されたミリ秒。負の値は 0、表せないほど大きい値や NaN は None
+pub fn duration_from_ms(ms: f64) -> Option<Duration> {
+    if ms.is_nan() {
+        return None;
+    }
+    Duration::try_from_secs_f64(ms.max(0.0) / 1000.0).ok()
+}
+
+// 周波数から周期を求める。周期が 0 になるほど高い周波数は None
+pub fn period_from_hz(hz: f64) -> Option<Duration> {
+    if !(hz.is_finite() && hz > 0.0) {
+        return None;
+    }
+    Duration::try_from_secs_f64(1.0 / hz)
+        .ok()
+        .filter(|period| !period.is_zero())
+}
+
+// 前回の予定時刻から一周期進める。処理が周期より長くかかったときは、
+// 遅れを取り戻そうと続けて呼ばないよう、間に合う次の周期まで飛ばす
+pub fn next_tick(previous: Duration, now: Duration, period: Duration) -> Duration {
+    let next = previous + period;
+    if now <= next {
+        return next;
+    }
+    let behind = (now - next).as_nanos() % period.as_nanos();
+    now - Duration::from_nanos(behind as u64) + period
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn clock_stops_while_paused() {
+        let pause = Arc::new(PauseState::default());
+        let clock = Clock::new(Arc::clone(&pause));
+        let stop_flag = AtomicBool::new(false);
+
+        assert!(clock.sleep(Duration::from_millis(20), &stop_flag));
+        assert!(clock.now() >= Duration::from_millis(20));
+
+        pause.pause();
+        let paused_at = clock.now();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(clock.now(), paused_at);
+        pause.resume();
+
+        stop_flag.store(true, Ordering::SeqCst);
+        assert!(!clock.sleep(Duration::from_secs(10), &stop_flag));
+    }
+
+    #[test]
+    fn rejects_unrepresentable_durations() {
+        assert_eq!(duration_from_ms(16.0), Some(Duration::from_millis(16)));
+        assert_eq!(duration_from_ms(-5.0), Some(Duration::ZERO));
+        assert_eq!(duration_from_ms(f64::INFINITY), None);
+        assert_eq!(duration_from_ms(1e300), None);
+        assert_eq!(duration_from_ms(f64::NAN), None);
+
+        assert_eq!(period_from_hz(100.0), Some(Duration::from_millis(10)));
+        assert_eq!(period_from_hz(0.0), None);
+        assert_eq!(period_from_hz(f64::INFINITY), None);
+        assert_eq!(period_from_hz(1e10), None);
+        // 周期が表せないほど低い周波数
+        assert_eq!(period_from_hz(1e-300), None);
+    }
+
+    #[test]
+    fn next_tick_keeps_phase() {
+        let ms = Duration::from_millis;
+        assert_eq!(next_tick(ms(0), ms(3), ms(10)), ms(10));
+        assert_eq!(next_tick(ms(10), ms(20), ms(10)), ms(20));
+        // 20 と 30 に間に合わなかったので 40 まで飛ばす
+        assert_eq!(next_tick(ms(10), ms(35), ms(10)), ms(40));
+    }
+
+    // 精度の確認用。`cargo test --release timing_accuracy -- --ignored --nocapture`
+    // Linux で測った結果 (p50 / p99):
+    //   thread::sleep(16ms)   136µs / 2.1ms
+    //   sleep_precise(16ms)   1.9µs / 2.9ms
+    //   sleep_precise(1ms)    0.6µs / 80µs
+    //   every(60) tick error  0.8µs / 530µs
+    // 負荷の高い CI でも落ちないよう、中央値だけを緩めの上限で確かめる
+    #[test]
+    #[ignore]
+    fn timing_accuracy() {
+        const MAX_MEDIAN_ERROR: Duration = Duration::from_micros(500);
+
+        fn report(name: &str, mut errors: Vec<Duration>) -> Duration {
+            errors.sort();
+            let mean = errors.iter().sum::<Duration>() / errors.len() as u32;
+            let median = errors[errors.len() / 2];
+            println!(
+                "{:<24} mean {:>9.3?}  p50 {:>9.3?}  p99 {:>9.3?}  max {:>9.3?}",
+                name,
+                mean,
+                median,
+                errors[errors.len() * 99 / 100],
+                errors[errors.len() - 1],
+            );
+            median
+        }
+
+        let clock = Clock::new(Arc::new(PauseState::default()));
+        let stop_flag = AtomicBool::new(false);
+        for ms in [1, 5, 16] {
+            let duration = Duration::from_millis(ms);
+            let measure = |f: &dyn Fn()| {
+                (0..200)
+                    .map(|_| {
+                        let start = Instant::now();
+                        f();
+                        start.elapsed().abs_diff(duration)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            report(
+                &format!("thread::sleep({}ms)", ms),
+                measure(&|| sleep(duration)),
+            );
+            let median = report(
+                &format!("sleep_precise({}ms)", ms),
+                measure(&|| {
+                    clock.sleep(duration, &stop_flag);
+                }),
+            );
+            assert!(median < MAX_MEDIAN_ERROR, "sleep_precise({}ms)", ms);
+        }
+
+        // 60Hz で 300 回。処理時間がばらついても予定時刻からずれないことを確かめる
+        let period = Duration::from_secs(1) / 60;
+        let mut next = clock.now();
+        let mut errors = Vec::new();
+        for tick in 0..300 {
+            sleep(Duration::from_micros(tick % 7 * 500));
+            next = next_tick(next, clock.now(), period);
+            clock.sleep_until(next, &stop_flag);
+            errors.push(clock.now().abs_diff(next));
+        }
+        let median = report("every(60) tick error", errors);
+        assert!(median < MAX_MEDIAN_ERROR, "every(60)");
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, Weak},
+};
+
+use device_query::Keycode;
+
+// 読まれないまま溜まり続けないように、古いエッジから捨てる
+const QUEUE_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(Keycode),
+    Button(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Pressed(Input),
+    Released(Input),
+}
+
+type Queue = Mutex<VecDeque<Edge>>;
+
+#[derive(Default)]
+struct TrackerState {
+    held: HashSet<Input>,
+    presses: u64,
+    last_pressed: HashMap<Input, u64>,
+    subscribers: Vec<Weak<Queue>>,
+}
+
+#[derive(Default)]
+pub struct EdgeTracker {
+    state: Mutex<TrackerState>,
+}
+impl EdgeTracker {
+    pub fn update(&self, held: HashSet<Input>) {
+        let mut state = self.state.lock().unwrap();
+
+        let mut edges: Vec<Edge> = held
+            .difference(&state.held)
+            .map(|input| Edge::Pressed(*input))
+            .collect();
+        edges.extend(
+            state
+                .held
+                .difference(&held)
+                .map(|input| Edge::Released(*input)),
+        );
+        state.held = held;
+        if edges.is_empty() {
+            return;
+        }
+
+        for edge in &edges {
+            if let Edge::Pressed(input) = edge {
+                state.presses += 1;
+                let presses = state.presses;
+                state.last_pressed.insert(*input, presses);
+            }
+        }
+
+        state.subscribers.retain(|queue| {
+            let Some(queue) = queue.upgrade() else {
+                return false;
+            };
+            let mut queue = queue.lock().unwrap();
+            for edge in &edges {
+                if queue.len() == QUEUE_CAPACITY {
+                    queue.pop_front();
+                }
+                queue.push_back(*edge);
+            }
+            true
+        });
+    }
+
+    pub fn subscribe(&self) -> Subscription {
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        self.state
+            .lock()
+            .unwrap()
+            .subscribers
+            .push(Arc::downgrade(&queue));
+        Subscription { queue }
+    }
+
+    pub fn token(&self) -> u64 {
+        self.state.lock().unwrap().presses
+    }
+    pub fn was_pressed_since(&self, input: Input, token: u64) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .last_pressed
+            .get(&input)
+            .is_some_and(|presses| *presses > token)
+    }
+}
+
+// 購読者ごとにキューを持つので、別の呼び出し元がエッジを奪うことはない
+pub struct Subscription {
+    queue: Arc<Queue>,
+}
+impl Subscription {
+    pub fn just_pressed(&self, input: Input) -> bool {
+        self.take(Edge::Pressed(input))
+    }
+    pub fn just_released(&self, input: Input) -> bool {
+        self.take(Edge::Released(input))
+    }
+
+    fn take(&self, edge: Edge) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        let len = queue.len();
+        queue.retain(|queued| *queued != edge);
+        queue.len() != len
+    }
+}
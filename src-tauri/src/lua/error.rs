@@ -0,0 +1,274 @@
+use std::{fmt, path::PathBuf};
+
+use super::{LimitExceeded, Permission};
+
+// スクリプト中の位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub source: String,
+    pub line: u32,
+}
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.line)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub location: Option<Location>,
+    pub traceback: Option<String>,
+}
+impl Trace {
+    fn prefix(&self) -> String {
+        prefix(self.location.as_ref())
+    }
+}
+
+fn prefix(location: Option<&Location>) -> String {
+    location
+        .map(|location| format!("{}: ", location))
+        .unwrap_or_default()
+}
+
+// 組み込み関数から投げ、ScriptError に変換するときに種類を見分ける
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{module} requires the '{}' permission (add `-- permissions: {}` to the script header)", permission.name(), permission.name())]
+pub struct PermissionDenied {
+    pub module: &'static str,
+    pub permission: Permission,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{function} failed: {message}")]
+pub struct InputBackendError {
+    pub function: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("interrupted")]
+pub struct Interrupted;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("Failed to load {}: {}{message}", path.display(), prefix(location.as_ref()))]
+    Load {
+        path: PathBuf,
+        message: String,
+        location: Option<Location>,
+    },
+    #[error("{}{error}", trace.prefix())]
+    Permission {
+        error: PermissionDenied,
+        trace: Trace,
+    },
+    #[error("{}{error}", trace.prefix())]
+    InputBackend {
+        error: InputBackendError,
+        trace: Trace,
+    },
+    #[error("{}{error}", trace.prefix())]
+    Limit { error: LimitExceeded, trace: Trace },
+    // ユーザーが止めた
+    #[error("interrupted")]
+    Interrupted,
+    #[error("{}{message}", trace.prefix())]
+    Runtime { message: String, trace: Trace },
+}
+impl ScriptError {
+    pub fn load(path: impl Into<PathBuf>, err: impl fmt::Display) -> Self {
+        ScriptError::Load {
+            path: path.into(),
+            message: err.to_string(),
+            location: None,
+        }
+    }
+    // 読み込み中の構文エラーなどは Load として扱う
+    pub fn from_load(path: impl Into<PathBuf>, err: mlua::Error) -> Self {
+        match ScriptError::from(err) {
+            ScriptError::Runtime { message, trace } => ScriptError::Load {
+                path: path.into(),
+                message,
+                location: trace.location,
+            },
+            err => err,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            ScriptError::Load { location, .. } => location.as_ref(),
+            ScriptError::Interrupted => None,
+            ScriptError::Permission { trace, .. }
+            | ScriptError::InputBackend { trace, .. }
+            | ScriptError::Limit { trace, .. }
+            | ScriptError::Runtime { trace, .. } => trace.location.as_ref(),
+        }
+    }
+    pub fn traceback(&self) -> Option<&str> {
+        match self {
+            ScriptError::Load { .. } | ScriptError::Interrupted => None,
+            ScriptError::Permission { trace, .. }
+            | ScriptError::InputBackend { trace, .. }
+            | ScriptError::Limit { trace, .. }
+            | ScriptError::Runtime { trace, .. } => trace.traceback.as_deref(),
+        }
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(err: mlua::Error) -> Self {
+        let mut traceback = None;
+        let mut current = &err;
+        let message = loop {
+            match current {
+                mlua::Error::CallbackError {
+                    traceback: callback_traceback,
+                    cause,
+                } => {
+                    traceback.get_or_insert_with(|| callback_traceback.clone());
+                    current = cause;
+                }
+                mlua::Error::WithContext { cause, .. } => current = cause,
+                mlua::Error::ExternalError(inner) => {
+                    let trace = Trace {
+                        location: traceback.as_deref().and_then(traceback_location),
+                        traceback,
+                    };
+                    if inner.is::<Interrupted>() {
+                        return ScriptError::Interrupted;
+                    }
+                    if let Some(error) = inner.downcast_ref::<PermissionDenied>() {
+                        return ScriptError::Permission {
+                            error: error.clone(),
+                            trace,
+                        };
+                    }
+                    if let Some(error) = inner.downcast_ref::<InputBackendError>() {
+                        return ScriptError::InputBackend {
+                            error: error.clone(),
+                            trace,
+                        };
+                    }
+                    if let Some(error) = inner.downcast_ref::<LimitExceeded>() {
+                        return ScriptError::Limit {
+                            error: *error,
+                            trace,
+                        };
+                    }
+                    return ScriptError::Runtime {
+                        message: inner.to_string(),
+                        trace,
+                    };
+                }
+                mlua::Error::RuntimeError(message) | mlua::Error::SyntaxError { message, .. } => {
+                    break message.clone()
+                }
+                err => break err.to_string(),
+            }
+        };
+
+        // Lua 側で起きたエラーは、メッセージの先頭に位置、末尾にトレースバックが付いている
+        let (message, message_traceback) = match message.split_once("\nstack traceback:\n") {
+            Some((message, traceback)) => (message.to_string(), Some(traceback.to_string())),
+            None => (message, None),
+        };
+        let (location, message) = match split_location(&message) {
+            Some((location, rest)) => (Some(location), rest.to_string()),
+            None => (
+                traceback.as_deref().and_then(traceback_location),
+                message.clone(),
+            ),
+        };
+        ScriptError::Runtime {
+            message,
+            trace: Trace {
+                location,
+                traceback: traceback.or(message_traceback),
+            },
+        }
+    }
+}
+
+// "path:12: message" を分ける。Windows のドライブ名を誤認しないよう、行番号が続くコロンを探す
+fn split_location(text: &str) -> Option<(Location, &str)> {
+    text.match_indices(':').find_map(|(index, _)| {
+        let source = &text[..index];
+        let (line, rest) = text[index + 1..].split_once(':')?;
+        let line = line.parse().ok()?;
+        (!source.is_empty() && !source.contains('\n')).then(|| {
+            (
+                Location {
+                    source: source.to_string(),
+                    line,
+                },
+                rest.trim_start(),
+            )
+        })
+    })
+}
+
+// 組み込み関数の中で起きたエラーは、それを呼んだスクリプトの位置を使う
+fn traceback_location(traceback: &str) -> Option<Location> {
+    traceback
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("[C]") && !line.starts_with("stack traceback"))
+        .find_map(|line| split_location(line).map(|(location, _)| location))
+}
+
+#[cfg(test)]
+mod tests {
+    use mlua::{Function, Lua};
+
+    use super::*;
+
+    fn run(source: &str) -> ScriptError {
+        let lua = Lua::new();
+        lua.globals()
+            .set(
+                "denied",
+                lua.create_function(|_, ()| -> mlua::Result<()> {
+                    Err(mlua::Error::external(PermissionDenied {
+                        module: "keyboard",
+                        permission: Permission::Input,
+                    }))
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        lua.load(source)
+            .set_name("@C:\\scripts\\test.lua")
+            .exec()
+            .unwrap();
+        let main: Function = lua.globals().get("Main").unwrap();
+        let thread = lua.create_thread(main).unwrap();
+        thread.resume::<()>(()).unwrap_err().into()
+    }
+
+    #[test]
+    fn runtime_error_has_location() {
+        let err = run("function Main()\n  local x = nil\n  return x.y\nend");
+        let ScriptError::Runtime { message, trace } = &err else {
+            panic!("unexpected error: {:?}", err);
+        };
+        assert_eq!(message, "attempt to index nil with 'y'");
+        assert_eq!(
+            trace.location,
+            Some(Location {
+                source: "C:\\scripts\\test.lua".to_string(),
+                line: 3
+            })
+        );
+        assert!(trace.traceback.is_some());
+    }
+
+    #[test]
+    fn callback_error_keeps_kind() {
+        let err = run("function Main()\n  denied()\nend");
+        assert!(matches!(err, ScriptError::Permission { .. }));
+        assert_eq!(err.location().map(|location| location.line), Some(2));
+        assert!(err.traceback().unwrap().contains("denied"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    sync::{atomic::AtomicBool, Mutex},
+    time::Duration,
+};
+
+use enigo::{Button, Coordinate, Direction};
+use rand::rngs::StdRng;
+
+use super::{
+    humanize::{self, SMOOTH_STEP},
+    model::Curve,
+    output::Output,
+};
+
+pub fn drag(
+    output: &mut Output,
+    rng: &Mutex<StdRng>,
+    from: (i32, i32),
+    to: (i32, i32),
+    button: Button,
+    duration: Duration,
+    exit_flag: &AtomicBool,
+) -> anyhow::Result<()> {
+    output.move_mouse(from.0, from.1, Coordinate::Abs)?;
+    output.button(button, Direction::Press)?;
+
+    let path = humanize::smooth_path(
+        &mut rng.lock().unwrap(),
+        from,
+        to,
+        duration,
+        Curve::EaseInOut,
+    );
+    let timer = output.timer();
+    let mut result = Ok(());
+    for (step, (x, y)) in path.into_iter().enumerate() {
+        if !output.wait(&timer, SMOOTH_STEP * step as u32, exit_flag) {
+            break;
+        }
+        if let Err(e) = output.move_mouse(x, y, Coordinate::Abs) {
+            result = Err(e);
+            break;
+        }
+    }
+
+    // 移動に失敗しても、停止されても離す
+    output.button(button, Direction::Release)?;
+    result
+}
+
+pub fn multi_click(
+    output: &mut Output,
+    button: Button,
+    count: u32,
+    interval: Duration,
+    exit_flag: &AtomicBool,
+) -> anyhow::Result<()> {
+    let timer = output.timer();
+    for i in 0..count {
+        if !output.wait(&timer, interval * i, exit_flag) {
+            break;
+        }
+        output.button(button, Direction::Click)?;
+    }
+    Ok(())
+}
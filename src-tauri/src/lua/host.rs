@@ -0,0 +1,43 @@
+use std::{path::PathBuf, time::Duration};
+
+use crossbeam::channel as ch;
+
+pub enum HostEvent {
+    // 停止を要求しても timeout の間に終わらなかった
+    ScriptStuck {
+        path: PathBuf,
+        timeout: Duration,
+    },
+    Notify {
+        title: String,
+        body: String,
+        icon: Option<String>,
+    },
+    Alert {
+        title: Option<String>,
+        message: String,
+        res: ch::Sender<bool>,
+    },
+    Confirm {
+        title: Option<String>,
+        message: String,
+        res: ch::Sender<bool>,
+    },
+    Prompt {
+        title: Option<String>,
+        message: String,
+        default: String,
+        res: ch::Sender<Option<String>>,
+    },
+    PickFile {
+        title: Option<String>,
+        directory: Option<String>,
+        filters: Vec<(String, Vec<String>)>,
+        res: ch::Sender<Option<String>>,
+    },
+    PickFolder {
+        title: Option<String>,
+        directory: Option<String>,
+        res: ch::Sender<Option<String>>,
+    },
+}
@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use mlua::{Table, Value};
+use rand::{rngs::StdRng, Rng};
+
+use super::model::Curve;
+
+const HOLD_BASE: Duration = Duration::from_millis(20);
+pub const SMOOTH_STEP: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy)]
+pub struct HumanizeOptions {
+    pub delay_ms: u64,
+    pub position: i32,
+}
+impl Default for HumanizeOptions {
+    fn default() -> Self {
+        HumanizeOptions {
+            delay_ms: 40,
+            position: 2,
+        }
+    }
+}
+impl HumanizeOptions {
+    pub fn from_options(opts: &Option<Table>) -> mlua::Result<Option<Self>> {
+        let Some(opts) = opts else {
+            return Ok(None);
+        };
+
+        match opts.get::<Value>("humanize")? {
+            Value::Nil | Value::Boolean(false) => Ok(None),
+            Value::Boolean(true) => Ok(Some(HumanizeOptions::default())),
+            Value::Table(table) => {
+                let default = HumanizeOptions::default();
+                Ok(Some(HumanizeOptions {
+                    delay_ms: table
+                        .get::<Option<u64>>("delay")?
+                        .unwrap_or(default.delay_ms),
+                    position: table
+                        .get::<Option<i32>>("position")?
+                        .unwrap_or(default.position),
+                }))
+            }
+            value => Err(mlua::Error::RuntimeError(format!(
+                "Invalid humanize option: {}",
+                value.type_name()
+            ))),
+        }
+    }
+
+    pub fn hold(&self, rng: &mut StdRng) -> Duration {
+        HOLD_BASE + Duration::from_millis(rng.random_range(0..=self.delay_ms))
+    }
+    pub fn offset(&self, rng: &mut StdRng) -> (i32, i32) {
+        let position = self.position.abs();
+        (
+            rng.random_range(-position..=position),
+            rng.random_range(-position..=position),
+        )
+    }
+}
+
+pub fn jitter(rng: &mut StdRng, ms: u64, jitter_ms: u64) -> Duration {
+    let jitter_ms = jitter_ms as i64;
+    Duration::from_millis((ms as i64 + rng.random_range(-jitter_ms..=jitter_ms)).max(0) as u64)
+}
+
+pub fn smooth_path(
+    rng: &mut StdRng,
+    from: (i32, i32),
+    to: (i32, i32),
+    duration: Duration,
+    curve: Curve,
+) -> Vec<(i32, i32)> {
+    let steps = (duration.as_millis() / SMOOTH_STEP.as_millis()).max(1) as usize;
+    let (x0, y0) = (from.0 as f64, from.1 as f64);
+    let (x3, y3) = (to.0 as f64, to.1 as f64);
+
+    let (dx, dy) = (x3 - x0, y3 - y0);
+    let spread = (dx * dx + dy * dy).sqrt() * 0.2;
+    let (nx, ny) = if spread > 0.0 {
+        (-dy / (spread * 5.0), dx / (spread * 5.0))
+    } else {
+        (0.0, 0.0)
+    };
+    let (o1, o2) = match curve {
+        Curve::Bezier => (
+            rng.random_range(-1.0..=1.0) * spread,
+            rng.random_range(-1.0..=1.0) * spread,
+        ),
+        _ => (0.0, 0.0),
+    };
+    let (x1, y1) = (x0 + dx / 3.0 + nx * o1, y0 + dy / 3.0 + ny * o1);
+    let (x2, y2) = (x0 + dx * 2.0 / 3.0 + nx * o2, y0 + dy * 2.0 / 3.0 + ny * o2);
+
+    (1..=steps)
+        .map(|step| {
+            let t = ease(curve, step as f64 / steps as f64);
+            let u = 1.0 - t;
+            let x = u * u * u * x0 + 3.0 * u * u * t * x1 + 3.0 * u * t * t * x2 + t * t * t * x3;
+            let y = u * u * u * y0 + 3.0 * u * u * t * y1 + 3.0 * u * t * t * y2 + t * t * t * y3;
+            (x.round() as i32, y.round() as i32)
+        })
+        .collect()
+}
+
+pub fn scroll_steps(dx: i32, dy: i32) -> Vec<(i32, i32)> {
+    let steps = dx.abs().max(dy.abs());
+    let (mut x, mut y) = (0, 0);
+    (1..=steps)
+        .map(|step| {
+            let next_x = (dx as f64 * step as f64 / steps as f64).round() as i32;
+            let next_y = (dy as f64 * step as f64 / steps as f64).round() as i32;
+            let delta = (next_x - x, next_y - y);
+            (x, y) = (next_x, next_y);
+            delta
+        })
+        .collect()
+}
+
+fn ease(curve: Curve, t: f64) -> f64 {
+    match curve {
+        Curve::Linear => t,
+        Curve::EaseIn => t * t,
+        Curve::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+        Curve::EaseInOut | Curve::Bezier => t * t * (3.0 - 2.0 * t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mlua::Lua;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_path() {
+        let path = |seed| {
+            smooth_path(
+                &mut StdRng::seed_from_u64(seed),
+                (0, 0),
+                (300, 200),
+                Duration::from_millis(200),
+                Curve::Bezier,
+            )
+        };
+        assert_eq!(path(1), path(1));
+        assert_ne!(path(1), path(2));
+    }
+
+    #[test]
+    fn path_ends_at_target() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for curve in [
+            Curve::Linear,
+            Curve::EaseIn,
+            Curve::EaseOut,
+            Curve::EaseInOut,
+            Curve::Bezier,
+        ] {
+            let path = smooth_path(
+                &mut rng,
+                (10, 20),
+                (-50, 400),
+                Duration::from_millis(100),
+                curve,
+            );
+            // 100ms を 5ms ごとに動かす
+            assert_eq!(path.len(), 20);
+            assert_eq!(path.last(), Some(&(-50, 400)));
+        }
+
+        let path = smooth_path(&mut rng, (0, 0), (5, 5), Duration::ZERO, Curve::Linear);
+        assert_eq!(path, [(5, 5)]);
+        let path = smooth_path(&mut rng, (0, 0), (100, 0), SMOOTH_STEP * 4, Curve::Linear);
+        assert_eq!(path, [(25, 0), (50, 0), (75, 0), (100, 0)]);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let duration = jitter(&mut rng, 100, 30);
+            assert!((70..=130).contains(&duration.as_millis()), "{:?}", duration);
+        }
+        // 負にはならない
+        for _ in 0..1000 {
+            assert!(jitter(&mut rng, 5, 30) <= Duration::from_millis(35));
+        }
+        assert_eq!(jitter(&mut rng, 42, 0), Duration::from_millis(42));
+    }
+
+    #[test]
+    fn parses_options() {
+        let lua = Lua::new();
+        let parse = |source: &str| {
+            let opts: Option<Table> = lua.load(source).eval().unwrap();
+            HumanizeOptions::from_options(&opts)
+        };
+
+        assert!(parse("nil").unwrap().is_none());
+        assert!(parse("{}").unwrap().is_none());
+        assert!(parse("{ humanize = false }").unwrap().is_none());
+
+        let options = parse("{ humanize = true }").unwrap().unwrap();
+        assert_eq!((options.delay_ms, options.position), (40, 2));
+        let options = parse("{ humanize = { delay = 10 } }").unwrap().unwrap();
+        assert_eq!((options.delay_ms, options.position), (10, 2));
+        let options = parse("{ humanize = { delay = 0, position = 5 } }")
+            .unwrap()
+            .unwrap();
+        assert_eq!((options.delay_ms, options.position), (0, 5));
+
+        assert!(parse("{ humanize = 'yes' }").is_err());
+    }
+}
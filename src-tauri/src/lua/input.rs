@@ -0,0 +1,548 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    thread::{sleep, spawn},
+    time::{Duration, Instant},
+};
+
+use device_query::{DeviceState, Keycode, MouseState};
+use serde::{Deserialize, Serialize};
+
+use super::edge::{EdgeTracker, Input};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// 送った入力が反映されるまでの猶予。この間に同じキー・ボタン・位置が変化したら送った入力とみなす
+const INJECTION_GRACE: Duration = Duration::from_millis(50);
+const FAILSAFE_MARGIN: i32 = 2;
+// 絶対座標への移動は丸めで少しずれることがある
+const POSITION_TOLERANCE: i32 = 2;
+
+static POLLER: LazyLock<InputPoller> = LazyLock::new(|| {
+    spawn(|| {
+        let state = DeviceState::new();
+        loop {
+            POLLER.poll(&state);
+            sleep(POLL_INTERVAL);
+        }
+    });
+    InputPoller::new()
+});
+
+trait StateSource {
+    fn keys(&self) -> Vec<Keycode>;
+    fn mouse(&self) -> MouseState;
+}
+impl StateSource for DeviceState {
+    fn keys(&self) -> Vec<Keycode> {
+        self.query_keymap()
+    }
+    fn mouse(&self) -> MouseState {
+        self.query_pointer()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+impl Corner {
+    // その方向へマウスを押し付けたときに止まる角にいるか。別の画面へ抜けられる辺は角にしない
+    fn contains(self, screens: &[Screen], (x, y): (i32, i32)) -> bool {
+        let (dx, dy) = match self {
+            Corner::TopLeft => (-1, -1),
+            Corner::TopRight => (1, -1),
+            Corner::BottomLeft => (-1, 1),
+            Corner::BottomRight => (1, 1),
+        };
+        let Some(screen) = screens.iter().find(|screen| screen.contains((x, y))) else {
+            return false;
+        };
+        let near = |position: i32, start: i32, length: i32, direction: i32| {
+            if direction < 0 {
+                position <= start + FAILSAFE_MARGIN
+            } else {
+                position >= start + length - 1 - FAILSAFE_MARGIN
+            }
+        };
+        let step = FAILSAFE_MARGIN + 1;
+        near(x, screen.x, screen.width, dx)
+            && near(y, screen.y, screen.height, dy)
+            && !screens.iter().any(|screen| {
+                screen.contains((x + dx * step, y)) || screen.contains((x, y + dy * step))
+            })
+    }
+}
+
+// 画面の位置と大きさ。メインでない画面は座標が負になることがある
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Screen {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+impl Screen {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+struct Failsafe {
+    corner: Corner,
+    screens: Vec<Screen>,
+    on_trigger: Box<dyn Fn() + Send + Sync>,
+}
+
+// ワーカーが実際に送った入力。ユーザーの操作と見分けるのに使う
+#[derive(Default)]
+struct Injected {
+    inputs: HashMap<Input, Instant>,
+    positions: VecDeque<((i32, i32), Instant)>,
+}
+impl Injected {
+    fn prune(&mut self, now: Instant) {
+        self.inputs
+            .retain(|_, at| now.duration_since(*at) < INJECTION_GRACE);
+        // 最後に動かした位置は、次に動かすまで残しておく
+        while self.positions.len() > 1
+            && self
+                .positions
+                .front()
+                .is_some_and(|(_, at)| now.duration_since(*at) >= INJECTION_GRACE)
+        {
+            self.positions.pop_front();
+        }
+    }
+
+    fn is_injected_input(&self, input: Input) -> bool {
+        self.inputs.contains_key(&input)
+    }
+    fn is_injected_position(&self, (x, y): (i32, i32)) -> bool {
+        self.positions.iter().any(|((injected_x, injected_y), _)| {
+            (x - injected_x).abs() <= POSITION_TOLERANCE
+                && (y - injected_y).abs() <= POSITION_TOLERANCE
+        })
+    }
+}
+
+type Snapshot = (Vec<Keycode>, MouseState);
+
+fn held_inputs((keys, mouse): &Snapshot) -> HashSet<Input> {
+    keys.iter()
+        .map(|key| Input::Key(*key))
+        .chain(
+            mouse
+                .button_pressed
+                .iter()
+                .enumerate()
+                .filter(|(_, pressed)| **pressed)
+                .map(|(index, _)| Input::Button(index)),
+        )
+        .collect()
+}
+
+struct InputPoller {
+    last_input: Mutex<Instant>,
+    real_inputs: AtomicU64,
+    previous: Mutex<Option<Snapshot>>,
+    injected: Mutex<Injected>,
+    failsafe: Mutex<Option<Failsafe>>,
+    edges: EdgeTracker,
+}
+impl InputPoller {
+    fn new() -> Self {
+        InputPoller {
+            last_input: Mutex::new(Instant::now()),
+            real_inputs: AtomicU64::new(0),
+            previous: Mutex::new(None),
+            injected: Mutex::new(Injected::default()),
+            failsafe: Mutex::new(None),
+            edges: EdgeTracker::default(),
+        }
+    }
+
+    fn poll(&self, source: &impl StateSource) {
+        let current = (source.keys(), source.mouse());
+
+        let mut previous = self.previous.lock().unwrap();
+        if previous.as_ref() == Some(&current) {
+            return;
+        }
+
+        let held = held_inputs(&current);
+        if let Some(previous) = previous.as_ref() {
+            // スクリプトが暴走していても止められるよう、送った入力かどうかに関わらず確認する
+            if let Some(failsafe) = &*self.failsafe.lock().unwrap() {
+                let contains = |(_, mouse): &Snapshot| {
+                    failsafe.corner.contains(&failsafe.screens, mouse.coords)
+                };
+                if contains(&current) && !contains(previous) {
+                    (failsafe.on_trigger)();
+                }
+            }
+
+            if self.is_real(previous, &current, &held) {
+                *self.last_input.lock().unwrap() = Instant::now();
+                self.real_inputs.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        self.edges.update(held);
+        *previous = Some(current);
+    }
+
+    // 変化のうち、ワーカーが送っていないものがあればユーザーの操作
+    fn is_real(&self, previous: &Snapshot, current: &Snapshot, held: &HashSet<Input>) -> bool {
+        let mut injected = self.injected.lock().unwrap();
+        injected.prune(Instant::now());
+
+        let moved = previous.1.coords != current.1.coords
+            && !injected.is_injected_position(current.1.coords);
+        moved
+            || held
+                .symmetric_difference(&held_inputs(previous))
+                .any(|input| !injected.is_injected_input(*input))
+    }
+
+    fn inject_input(&self, input: Input) {
+        self.injected
+            .lock()
+            .unwrap()
+            .inputs
+            .insert(input, Instant::now());
+    }
+    fn inject_position(&self, position: (i32, i32)) {
+        self.injected
+            .lock()
+            .unwrap()
+            .positions
+            .push_back((position, Instant::now()));
+    }
+}
+
+// アプリ起動時に呼んでおくと、起動直後から入力を追跡できる
+pub fn start_input_poller() {
+    LazyLock::force(&POLLER);
+}
+
+pub fn idle_time() -> Duration {
+    POLLER.last_input.lock().unwrap().elapsed()
+}
+
+// ユーザーによる入力の回数。増えていればその間に操作があった
+pub fn real_inputs() -> u64 {
+    POLLER.real_inputs.load(Ordering::SeqCst)
+}
+
+pub fn edges() -> &'static EdgeTracker {
+    &POLLER.edges
+}
+
+// 送ったキーやボタンを知らせておくと、その変化をユーザーの操作として扱わない
+pub fn injected_input(input: Input) {
+    POLLER.inject_input(input);
+}
+// 動かした先のカーソル位置
+pub fn injected_position(position: (i32, i32)) {
+    POLLER.inject_position(position);
+}
+
+// key が押されるたびに f を呼ぶ
+pub fn on_hotkey(key: Keycode, f: impl Fn() + Send + 'static) {
+    let subscription = POLLER.edges.subscribe();
+    spawn(move || loop {
+        sleep(POLL_INTERVAL);
+        if subscription.just_pressed(Input::Key(key)) {
+            f();
+        }
+    });
+}
+
+// ユーザーがマウスを画面の角に動かしたら on_trigger を呼ぶ
+pub fn set_failsafe(
+    corner: Option<Corner>,
+    screens: Vec<Screen>,
+    on_trigger: impl Fn() + Send + Sync + 'static,
+) {
+    *POLLER.failsafe.lock().unwrap() = corner.map(|corner| Failsafe {
+        corner,
+        screens,
+        on_trigger: Box::new(on_trigger),
+    });
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LockState {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+}
+
+#[cfg(target_os = "windows")]
+pub fn lock_state() -> LockState {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, VK_CAPITAL, VK_NUMLOCK};
+
+    // 下位ビットがトグル状態
+    let toggled = |key: u16| unsafe { GetKeyState(key as i32) } & 1 != 0;
+    LockState {
+        caps_lock: toggled(VK_CAPITAL.0),
+        num_lock: toggled(VK_NUMLOCK.0),
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn lock_state() -> LockState {
+    use std::ptr;
+
+    use x11::xlib::{XCloseDisplay, XOpenDisplay, XkbGetIndicatorState};
+
+    const XKB_USE_CORE_KBD: u32 = 0x0100;
+
+    unsafe {
+        let display = XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return LockState::default();
+        }
+        let mut indicators = 0;
+        XkbGetIndicatorState(display, XKB_USE_CORE_KBD, &mut indicators);
+        XCloseDisplay(display);
+
+        // 標準のキーマップでは Caps Lock が 0 番目、Num Lock が 1 番目のインジケータ
+        LockState {
+            caps_lock: indicators & 0b01 != 0,
+            num_lock: indicators & 0b10 != 0,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn lock_state() -> LockState {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const FLAG_ALPHA_SHIFT: u64 = 0x0001_0000;
+
+    let flags = unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) };
+    // macOS には Num Lock がない
+    LockState {
+        caps_lock: flags & FLAG_ALPHA_SHIFT != 0,
+        num_lock: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, sync::Arc};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockSource {
+        keys: RefCell<Vec<Keycode>>,
+        buttons: RefCell<Vec<bool>>,
+        coords: RefCell<(i32, i32)>,
+    }
+    impl MockSource {
+        fn set(&self, keys: &[Keycode], buttons: &[bool]) {
+            *self.keys.borrow_mut() = keys.to_vec();
+            *self.buttons.borrow_mut() = buttons.to_vec();
+        }
+        fn move_to(&self, x: i32, y: i32) {
+            *self.coords.borrow_mut() = (x, y);
+        }
+    }
+    impl StateSource for MockSource {
+        fn keys(&self) -> Vec<Keycode> {
+            self.keys.borrow().clone()
+        }
+        fn mouse(&self) -> MouseState {
+            MouseState {
+                coords: *self.coords.borrow(),
+                button_pressed: self.buttons.borrow().clone(),
+            }
+        }
+    }
+
+    const A: Input = Input::Key(Keycode::A);
+
+    #[test]
+    fn subscribers_do_not_steal_edges() {
+        let poller = InputPoller::new();
+        let source = MockSource::default();
+        poller.poll(&source);
+
+        let first = poller.edges.subscribe();
+        let second = poller.edges.subscribe();
+        source.set(&[Keycode::A], &[]);
+        poller.poll(&source);
+
+        assert!(first.just_pressed(A));
+        assert!(second.just_pressed(A));
+        assert!(!first.just_pressed(A));
+        assert!(!second.just_pressed(A));
+    }
+
+    #[test]
+    fn short_press_between_checks_is_kept() {
+        let poller = InputPoller::new();
+        let source = MockSource::default();
+        poller.poll(&source);
+        let subscription = poller.edges.subscribe();
+
+        source.set(&[Keycode::A], &[]);
+        poller.poll(&source);
+        source.set(&[], &[]);
+        poller.poll(&source);
+
+        assert!(subscription.just_pressed(A));
+        assert!(subscription.just_released(A));
+        assert!(!subscription.just_released(A));
+    }
+
+    #[test]
+    fn held_before_subscribe_is_not_an_edge() {
+        let poller = InputPoller::new();
+        let source = MockSource::default();
+        source.set(&[Keycode::A], &[false, true]);
+        poller.poll(&source);
+        let subscription = poller.edges.subscribe();
+        poller.poll(&source);
+
+        assert!(!subscription.just_pressed(A));
+        assert!(!subscription.just_pressed(Input::Button(1)));
+
+        source.set(&[], &[false, false]);
+        poller.poll(&source);
+        assert!(subscription.just_released(A));
+        assert!(subscription.just_released(Input::Button(1)));
+    }
+
+    #[test]
+    fn injected_input_is_not_real() {
+        let poller = InputPoller::new();
+        let source = MockSource::default();
+        poller.poll(&source);
+        let real_inputs = || poller.real_inputs.load(Ordering::SeqCst);
+
+        poller.inject_input(A);
+        source.set(&[Keycode::A], &[]);
+        poller.poll(&source);
+        assert_eq!(real_inputs(), 0);
+
+        // 送っている最中でも、送っていないキーはユーザーの操作
+        poller.inject_input(A);
+        source.set(&[Keycode::A, Keycode::B], &[]);
+        poller.poll(&source);
+        assert_eq!(real_inputs(), 1);
+
+        poller.inject_position((100, 100));
+        source.move_to(101, 100);
+        poller.poll(&source);
+        assert_eq!(real_inputs(), 1);
+        source.move_to(300, 200);
+        poller.poll(&source);
+        assert_eq!(real_inputs(), 2);
+    }
+
+    #[test]
+    fn failsafe_ignores_injection() {
+        let poller = InputPoller::new();
+        let source = MockSource::default();
+        source.move_to(500, 500);
+        poller.poll(&source);
+
+        let triggered = Arc::new(AtomicU64::new(0));
+        *poller.failsafe.lock().unwrap() = Some(Failsafe {
+            corner: Corner::TopLeft,
+            screens: vec![screen(0, 0, 1920, 1080)],
+            on_trigger: Box::new({
+                let triggered = Arc::clone(&triggered);
+                move || {
+                    triggered.fetch_add(1, Ordering::SeqCst);
+                }
+            }),
+        });
+
+        // スクリプトが角へ動かした場合も止める
+        poller.inject_position((0, 0));
+        source.move_to(0, 0);
+        poller.poll(&source);
+        assert_eq!(triggered.load(Ordering::SeqCst), 1);
+
+        // 角にいる間は繰り返さない
+        source.move_to(1, 1);
+        poller.poll(&source);
+        assert_eq!(triggered.load(Ordering::SeqCst), 1);
+    }
+
+    fn screen(x: i32, y: i32, width: i32, height: i32) -> Screen {
+        Screen {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn corners() {
+        let screens = [screen(0, 0, 1920, 1080)];
+        assert!(Corner::TopLeft.contains(&screens, (0, 0)));
+        assert!(Corner::TopLeft.contains(&screens, (2, 2)));
+        assert!(Corner::BottomRight.contains(&screens, (1919, 1079)));
+        assert!(!Corner::TopRight.contains(&screens, (0, 0)));
+        assert!(!Corner::TopLeft.contains(&screens, (100, 0)));
+        assert!(!Corner::TopLeft.contains(&screens, (-1, -1)));
+    }
+
+    #[test]
+    fn corners_with_multiple_screens() {
+        // メインの左に、少し下にずらした画面がある
+        let screens = [screen(0, 0, 1920, 1080), screen(-1280, 200, 1280, 1024)];
+        assert!(Corner::TopLeft.contains(&screens, (-1280, 200)));
+        assert!(!Corner::TopLeft.contains(&screens, (-600, 200)));
+        assert!(!Corner::TopLeft.contains(&screens, (-1, 201)));
+        // メインの左上は左の画面の上にあり、そこから左へは動けない
+        assert!(Corner::TopLeft.contains(&screens, (0, 0)));
+        assert!(!Corner::TopLeft.contains(&screens, (0, 300)));
+        assert!(Corner::BottomLeft.contains(&screens, (-1280, 1223)));
+        assert!(Corner::BottomRight.contains(&screens, (-1, 1223)));
+        // 右の画面へ抜けられる
+        assert!(!Corner::TopRight.contains(&screens, (-1, 200)));
+        assert!(Corner::BottomRight.contains(&screens, (1919, 1079)));
+        assert!(!Corner::TopLeft.contains(&screens, (-1300, 0)));
+    }
+
+    #[test]
+    fn was_pressed_since_token() {
+        let poller = InputPoller::new();
+        let source = MockSource::default();
+        poller.poll(&source);
+
+        source.set(&[Keycode::A], &[]);
+        poller.poll(&source);
+        let token = poller.edges.token();
+        assert!(!poller.edges.was_pressed_since(A, token));
+
+        source.set(&[], &[]);
+        poller.poll(&source);
+        source.set(&[Keycode::B], &[]);
+        poller.poll(&source);
+        assert!(!poller.edges.was_pressed_since(A, token));
+        assert!(poller
+            .edges
+            .was_pressed_since(Input::Key(Keycode::B), token));
+
+        source.set(&[Keycode::A], &[]);
+        poller.poll(&source);
+        assert!(poller.edges.was_pressed_since(A, token));
+    }
+}
@@ -1,176 +1,264 @@
 use std::{
+    collections::{BTreeSet, HashMap},
     fs,
     path::Path,
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread::spawn,
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
 use crossbeam::channel as ch;
-use device_query::{DeviceState, Keycode};
-use enigo::{Direction, Enigo, Key, Keyboard, Mouse, Settings};
-use mlua::{Function, Lua, LuaOptions, StdLib, VmState};
+use device_query::Keycode;
+use mlua::{
+    ExternalResult, FromLua, Function, IntoLua, Lua, LuaOptions, StdLib, Table, Value, VmState,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use resolve_path::PathResolveExt;
 
-use super::model::{ButtonSend, Coordinate, KeySend};
+use super::{
+    clock::{self, Clock},
+    edge::{Input, Subscription},
+    error::{Interrupted, PermissionDenied, ScriptError},
+    humanize::{self, HumanizeOptions},
+    input,
+    limits::{Budget, LimitExceeded, Limits},
+    manifest::{Permission, UserInputPolicy},
+    model::{Button, Coordinate, Curve, Key},
+    pause::PauseState,
+    sequence,
+    throttle::RateLimits,
+    timeline::wait_until,
+    worker::{LuaEvent, Worker, WorkerContext},
+    HostEvent, MacroTimeline,
+};
 
-pub enum LuaEvent {
-    KeyboardPress {
-        key: KeySend,
-    },
-    KeyboardRelease {
-        key: KeySend,
-    },
-    KeyboardClick {
-        key: KeySend,
-    },
-    KeyboardIsPressing {
-        key: Keycode,
-        res: ch::Sender<bool>,
-    },
-    KeyboardCharPress {
-        char: char,
-    },
-    KeyboardCharRelease {
-        char: char,
-    },
-    KeyboardCharClick {
-        char: char,
-    },
-    MouseGetPos {
-        res: ch::Sender<(i32, i32)>,
-    },
-    MouseMove {
-        x: i32,
-        y: i32,
-        coordinate: Coordinate,
-    },
-    MousePress {
-        button: ButtonSend,
-    },
-    MouseRelease {
-        button: ButtonSend,
-    },
-    MouseClick {
-        button: ButtonSend,
-    },
-    MouseIsPressing {
-        button: u32,
-        res: ch::Sender<bool>,
-    },
+const MULTI_CLICK_INTERVAL: u64 = 50;
+const USER_INPUT_POLL: Duration = Duration::from_millis(20);
+// 開始・再開の直後はユーザーの操作が落ち着くまで監視しない
+const USER_INPUT_SETTLE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptOptions {
+    pub permissions: BTreeSet<Permission>,
+    pub limits: Limits,
+    pub rate_limits: RateLimits,
+    pub on_user_input: UserInputPolicy,
 }
 
 pub struct LuaInstance {
     pub lua: Lua,
     pub exit_flag: Arc<AtomicBool>,
+    abandoned: Arc<AtomicBool>,
+    budget: Arc<Budget>,
+    pause: Arc<PauseState>,
+    on_user_input: UserInputPolicy,
+    host: ch::Sender<HostEvent>,
 }
 impl LuaInstance {
     pub fn create_from_file<FP: AsRef<Path>, SP: AsRef<Path>>(
         file_path: FP,
         std_path: SP,
-    ) -> anyhow::Result<Self> {
-        let lua = Lua::new_with(StdLib::ALL, LuaOptions::default())?;
-        let (sender, receiver) = ch::unbounded::<LuaEvent>();
-        register_builtins(&lua, Arc::new(sender), std_path.as_ref())?;
-        spawn(move || {
-            let mut enigo = Enigo::new(&Settings::default())?;
-            let state = DeviceState::new();
-
-            while let Ok(event) = receiver.recv() {
-                match event {
-                    LuaEvent::KeyboardPress { key } => {
-                        enigo.key(key.into(), Direction::Press)?;
-                    }
-                    LuaEvent::KeyboardRelease { key } => {
-                        enigo.key(key.into(), Direction::Release)?;
-                    }
-                    LuaEvent::KeyboardClick { key } => {
-                        enigo.key(key.into(), Direction::Click)?;
-                    }
-                    LuaEvent::KeyboardIsPressing { key, res } => {
-                        res.send(state.query_keymap().contains(&key))?;
-                    }
-
-                    LuaEvent::KeyboardCharPress { char } => {
-                        enigo.key(Key::Unicode(char), Direction::Press)?;
-                    }
-                    LuaEvent::KeyboardCharRelease { char } => {
-                        enigo.key(Key::Unicode(char), Direction::Release)?;
-                    }
-                    LuaEvent::KeyboardCharClick { char } => {
-                        enigo.key(Key::Unicode(char), Direction::Click)?;
-                    }
-                    LuaEvent::MouseGetPos { res, .. } => {
-                        res.send(state.query_pointer().coords)?;
-                    }
-                    LuaEvent::MouseMove { x, y, coordinate } => {
-                        enigo.move_mouse(x, y, coordinate.into())?;
-                    }
-                    LuaEvent::MousePress { button } => {
-                        enigo.button(button.into(), Direction::Press)?;
-                    }
-                    LuaEvent::MouseRelease { button } => {
-                        enigo.button(button.into(), Direction::Release)?;
-                    }
-                    LuaEvent::MouseClick { button } => {
-                        enigo.button(button.into(), Direction::Click)?;
-                    }
-                    LuaEvent::MouseIsPressing { button, res } => {
-                        res.send(
-                            *state
-                                .query_pointer()
-                                .button_pressed
-                                .get(button as usize)
-                                .ok_or(mlua::Error::RuntimeError(format!(
-                                    "Invalid button index: {}",
-                                    button
-                                )))?,
-                        )?;
-                    }
-                }
-            }
-
-            Ok::<(), anyhow::Error>(())
-        });
-
+        options: &ScriptOptions,
+        host: ch::Sender<HostEvent>,
+    ) -> Result<Self, ScriptError> {
+        let path = file_path.as_ref();
+        let load_error = |err: &dyn std::fmt::Display| ScriptError::load(path, err);
+        let ScriptOptions {
+            permissions,
+            limits,
+            rate_limits,
+            on_user_input,
+        } = options.clone();
+        let lua = Lua::new_with(std_libs(&permissions), LuaOptions::default())
+            .map_err(|err| load_error(&err))?;
+        if let Some(memory_mb) = limits.memory_mb {
+            lua.set_memory_limit(memory_mb * 1024 * 1024)
+                .map_err(|err| load_error(&err))?;
+        }
+        let pause = Arc::new(PauseState::default());
+        let budget = Arc::new(Budget::new(limits, Arc::clone(&pause)));
         let exit_flag = Arc::new(AtomicBool::new(false));
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let rng = Arc::new(Mutex::new(StdRng::from_os_rng()));
+        let worker = Arc::new(Worker::new(WorkerContext {
+            rate_limits,
+            exit_flag: Arc::clone(&exit_flag),
+            abandoned: Arc::clone(&abandoned),
+            budget: Arc::clone(&budget),
+            pause: Arc::clone(&pause),
+            host: host.clone(),
+            rng: Arc::clone(&rng),
+        }));
+        register_builtins(
+            &lua,
+            &permissions,
+            worker,
+            std_path.as_ref(),
+            path.parent().unwrap_or(Path::new(".")),
+        )
+        .map_err(|err| load_error(&format!("{:#}", err)))?;
+        register_timing(
+            &lua,
+            Arc::clone(&rng),
+            Arc::clone(&exit_flag),
+            Arc::clone(&pause),
+        )
+        // 組み込み関数の登録後に有効にすると、スクリプトから書き換えられなくなる
+        .and_then(|()| lua.sandbox(true))
+        .map_err(|err| load_error(&err))?;
+
         {
             let exit_flag = Arc::clone(&exit_flag);
+            let budget = Arc::clone(&budget);
+            let pause = Arc::clone(&pause);
             lua.set_interrupt(move |_| {
+                if pause.is_paused() {
+                    pause.wait(&exit_flag);
+                }
                 if exit_flag.load(Ordering::SeqCst) {
-                    Err(mlua::Error::RuntimeError("interrupted".to_string()))
-                } else {
-                    Ok(VmState::Continue)
+                    return Err(match budget.exceeded() {
+                        Some(exceeded) => mlua::Error::external(exceeded),
+                        None => mlua::Error::external(Interrupted),
+                    });
+                }
+                if let Err(exceeded) = budget.tick() {
+                    exit_flag.store(true, Ordering::SeqCst);
+                    return Err(mlua::Error::external(exceeded));
                 }
+                Ok(VmState::Continue)
             });
         }
-        lua.load(std::fs::read(&file_path)?).exec()?;
+        // 位置がファイルのパスで表示されるように名前を付ける
+        lua.load(std::fs::read(path).map_err(|err| load_error(&err))?)
+            .set_name(format!("@{}", path.display()))
+            .exec()
+            .map_err(|err| ScriptError::from_load(path, budget.map_error(err)))?;
 
-        Ok(LuaInstance { lua, exit_flag })
+        Ok(LuaInstance {
+            lua,
+            exit_flag,
+            abandoned,
+            budget,
+            pause,
+            on_user_input,
+            host,
+        })
     }
-    pub fn execute(&self) -> mlua::Result<()> {
+    pub fn execute(&self) -> Result<(), ScriptError> {
         let entry: Function = self.lua.globals().get("Main")?;
         let thread = self.lua.create_thread(entry)?;
         self.exit_flag.store(false, Ordering::SeqCst);
-        thread.resume::<()>(())?;
-        Ok(())
+        self.budget.restart();
+
+        // sleep などで割り込みが来ない間も実行時間の上限で止める
+        let finished = Arc::new(AtomicBool::new(false));
+        if let Some(secs) = self.budget.limits().max_runtime_secs {
+            let finished = Arc::clone(&finished);
+            let exit_flag = Arc::clone(&self.exit_flag);
+            let budget = Arc::clone(&self.budget);
+            spawn(move || {
+                while let Some(deadline) = budget.deadline() {
+                    if !wait_until(deadline, &finished) {
+                        break;
+                    }
+                    // 待っている間に一時停止していれば期限が延びている
+                    if budget
+                        .deadline()
+                        .is_some_and(|deadline| Instant::now() < deadline)
+                    {
+                        continue;
+                    }
+                    budget.exceed(LimitExceeded::Runtime(secs));
+                    exit_flag.store(true, Ordering::SeqCst);
+                    break;
+                }
+            });
+        }
+
+        if self.on_user_input != UserInputPolicy::Ignore {
+            let finished = Arc::clone(&finished);
+            let exit_flag = Arc::clone(&self.exit_flag);
+            let pause = Arc::clone(&self.pause);
+            let policy = self.on_user_input;
+            let host = self.host.clone();
+            spawn(move || {
+                let mut baseline = None;
+                while !finished.load(Ordering::SeqCst) && !exit_flag.load(Ordering::SeqCst) {
+                    std::thread::sleep(USER_INPUT_POLL);
+                    if pause.is_paused() || baseline.is_none() {
+                        baseline = (input::idle_time() >= USER_INPUT_SETTLE && !pause.is_paused())
+                            .then(input::real_inputs);
+                        continue;
+                    }
+                    if baseline == Some(input::real_inputs()) {
+                        continue;
+                    }
+
+                    let body = if policy == UserInputPolicy::Pause {
+                        pause.pause();
+                        baseline = None;
+                        "ユーザーの操作を検出したため、スクリプトを一時停止しました"
+                    } else {
+                        exit_flag.store(true, Ordering::SeqCst);
+                        "ユーザーの操作を検出したため、スクリプトを停止しました"
+                    };
+                    let _ = host.send(HostEvent::Notify {
+                        title: "スクリプトを中断しました".to_string(),
+                        body: body.to_string(),
+                        icon: None,
+                    });
+                }
+            });
+        }
+
+        let result = thread.resume::<()>(());
+        finished.store(true, Ordering::SeqCst);
+        result.map_err(|err| self.budget.map_error(err).into())
     }
 
     pub fn stop(&self) -> anyhow::Result<()> {
         self.exit_flag.store(true, Ordering::SeqCst);
         Ok(())
     }
+
+    // 停止しても応答しないときに呼ぶ。ワーカーを終わらせて、押しているキーを離す
+    pub fn abandon(&self) {
+        self.abandoned.store(true, Ordering::SeqCst);
+        self.exit_flag.store(true, Ordering::SeqCst);
+        self.pause.resume();
+    }
+
+    // 次の割り込みか組み込み関数の呼び出しで止まる。押しているキーは離される
+    pub fn pause(&self) -> bool {
+        self.pause.pause()
+    }
+    pub fn resume(&self) -> bool {
+        self.pause.resume().is_some()
+    }
+    pub fn is_paused(&self) -> bool {
+        self.pause.is_paused()
+    }
 }
 
-fn register_builtins<P: AsRef<Path>>(
+pub(super) fn register_builtins<P: AsRef<Path>>(
     lua: &Lua,
-    channel: Arc<ch::Sender<LuaEvent>>,
+    permissions: &BTreeSet<Permission>,
+    worker: Arc<Worker>,
     std_path: P,
+    script_dir: &Path,
 ) -> anyhow::Result<()> {
     let std_path = std_path.as_ref().to_string_lossy().to_string();
+    let WorkerContext {
+        host,
+        exit_flag,
+        rng,
+        ..
+    } = worker.context().clone();
 
     macro_rules! declare_function {
         (
@@ -179,47 +267,69 @@ fn register_builtins<P: AsRef<Path>>(
                 $arg:ident: $arg_type:ty
             ),*
         ) => {{
-            let channel = Arc::clone(&channel);
+            let worker = Arc::clone(&worker);
             (
                 $name,
-                lua.create_function(move |_, $($arg: $arg_type),*| {
-                    channel.send($event).map_err(|e| {
-                        mlua::Error::RuntimeError(format!("Failed to send event: {}", e))
-                    })?;
-                    Ok(())
+                lua.create_function(move |_, ($($arg,)*): ($($arg_type,)*)| {
+                    worker.send($event)
                 })?,
             )
         }};
     }
 
-    lua.load(format!(
-        r#"package.path = package.path .. ";{}/?.lua""#,
-        std_path
-    ))
-    .exec()
-    .context("Failed to set package.path")?;
+    let globals = lua.globals();
+    if permissions.contains(&Permission::Filesystem) {
+        lua.load(format!(
+            r#"package.path = package.path .. ";{}/?.lua""#,
+            std_path
+        ))
+        .exec()
+        .context("Failed to set package.path")?;
+    } else {
+        // entry.lua の読み込みには、std_path の中だけを読める require を使う
+        let std_path = std_path.clone();
+        globals.set(
+            "require",
+            lua.create_function(move |lua, name: String| {
+                if !name
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '.')
+                {
+                    return Err(mlua::Error::runtime(format!("Invalid module: {}", name)));
+                }
+                let path = format!("{}/{}.lua", std_path, name.replace('.', "/"));
+                lua.load(fs::read(&path).into_lua_err()?)
+                    .set_name(format!("@{}", path))
+                    .call::<Value>(())
+            })?,
+        )?;
+    }
     lua.load(fs::read(format!("{}/entry.lua", std_path))?)
         .exec()
         .context("Failed to load entry.lua")?;
-
-    let globals = lua.globals();
+    if !permissions.contains(&Permission::Filesystem) {
+        globals.set(
+            "require",
+            denied_function(lua, "require", Permission::Filesystem)?,
+        )?;
+    }
 
     declare_function!(
         "press",
         LuaEvent::KeyboardPress {
-            key: KeySend::from_str(&key).map_err(|_| {
+            key: Key::from_str(&key).map_err(|_| {
                 mlua::Error::RuntimeError(format!("Invalid key: {}", key))
             })?,
         },
         key: String
     );
 
-    globals.set("keyboard", {
+    set_granted(lua, permissions, "keyboard", Permission::Input, || {
         let keyboard = lua.create_table_from([
             declare_function!(
                 "press",
                 LuaEvent::KeyboardPress {
-                    key: KeySend::from_str(&key).map_err(|_| {
+                    key: Key::from_str(&key).map_err(|_| {
                         mlua::Error::RuntimeError(format!("Invalid key: {}", key))
                     })?,
                 },
@@ -228,7 +338,7 @@ fn register_builtins<P: AsRef<Path>>(
             declare_function!(
                 "release",
                 LuaEvent::KeyboardRelease {
-                    key: KeySend::from_str(&key).map_err(|_| {
+                    key: Key::from_str(&key).map_err(|_| {
                         mlua::Error::RuntimeError(format!("Invalid key: {}", key))
                     })?,
                 },
@@ -237,29 +347,88 @@ fn register_builtins<P: AsRef<Path>>(
             declare_function!(
                 "click",
                 LuaEvent::KeyboardClick {
-                    key: KeySend::from_str(&key).map_err(|_| {
+                    key: Key::from_str(&key).map_err(|_| {
                         mlua::Error::RuntimeError(format!("Invalid key: {}", key))
                     })?,
+                    humanize: HumanizeOptions::from_options(&opts)?,
                 },
-                key: String
+                key: String,
+                opts: Option<Table>
             ),
             {
-                let channel = channel.clone();
+                let worker = Arc::clone(&worker);
                 (
                     "is_pressing",
                     lua.create_function(move |_, key: String| {
-                        let (sender, receiver) = ch::unbounded();
-                        channel
-                            .send(LuaEvent::KeyboardIsPressing {
-                                key: Keycode::from_str(&key).map_err(|_| {
-                                    mlua::Error::RuntimeError(format!("Invalid key: {}", key))
-                                })?,
-                                res: sender,
-                            })
-                            .map_err(|e| {
-                                mlua::Error::RuntimeError(format!("Failed to send event: {}", e))
-                            })?;
-                        Ok(receiver.recv().unwrap())
+                        let key = query_key(&key)?;
+                        Ok(worker
+                            .request(|res| LuaEvent::KeyboardIsPressing { key, res })?
+                            .unwrap_or(false))
+                    })?,
+                )
+            },
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "pressed",
+                    lua.create_function(move |lua, ()| {
+                        let keys = worker
+                            .request(|res| LuaEvent::KeyboardPressed { res })?
+                            .unwrap_or_default();
+                        lua.create_sequence_from(keys.into_iter().map(|keycode| {
+                            Key::from_keycode(keycode)
+                                .map(|key| key.name().to_string())
+                                .unwrap_or_else(|| keycode.to_string())
+                        }))
+                    })?,
+                )
+            },
+            (
+                "lock_state",
+                lua.create_function(|lua, ()| {
+                    let state = input::lock_state();
+                    lua.create_table_from([
+                        ("caps_lock", state.caps_lock),
+                        ("num_lock", state.num_lock),
+                    ])
+                })?,
+            ),
+            declare_function!(
+                "combo",
+                LuaEvent::KeyboardSequence {
+                    sequence: sequence::parse_combo(&spec).map_err(|e| {
+                        mlua::Error::RuntimeError(format!("Invalid combo {:?}: {}", spec, e))
+                    })?,
+                },
+                spec: String
+            ),
+            declare_function!(
+                "send",
+                LuaEvent::KeyboardSequence {
+                    sequence: sequence::parse_send(&spec).map_err(|e| {
+                        mlua::Error::RuntimeError(format!("Invalid send string {:?}: {}", spec, e))
+                    })?,
+                },
+                spec: String
+            ),
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "type",
+                    lua.create_function(move |_, (text, opts): (String, Option<Table>)| {
+                        let (delay_ms, jitter_ms) = match &opts {
+                            Some(opts) => (
+                                opts.get::<Option<u64>>("delay_ms")?.unwrap_or(0),
+                                opts.get::<Option<u64>>("jitter")?.unwrap_or(0),
+                            ),
+                            None => (0, 0),
+                        };
+
+                        worker.send(LuaEvent::KeyboardType {
+                            text,
+                            delay_ms,
+                            jitter_ms,
+                        })
                     })?,
                 )
             },
@@ -270,137 +439,743 @@ fn register_builtins<P: AsRef<Path>>(
                 declare_function!(
                     "press",
                     LuaEvent::KeyboardCharPress {
-                        char: char.chars().next().ok_or_else(|| {
-                            mlua::Error::RuntimeError("Invalid character".to_string())
-                        })?,
+                        char: single_char(&char)?,
                     },
                     char: String
                 ),
                 declare_function!(
                     "release",
                     LuaEvent::KeyboardCharRelease {
-                        char: char.chars().next().ok_or_else(|| {
-                            mlua::Error::RuntimeError("Invalid character".to_string())
-                        })?,
+                        char: single_char(&char)?,
                     },
                     char: String
                 ),
                 declare_function!(
                     "click",
                     LuaEvent::KeyboardCharClick {
-                        char: char.chars().next().ok_or_else(|| {
-                            mlua::Error::RuntimeError("Invalid character".to_string())
-                        })?,
+                        char: single_char(&char)?,
                     },
                     char: String
                 ),
             ])?,
         )?;
 
-        keyboard
+        Ok(keyboard)
     })?;
-    globals.set(
-        "mouse",
+    set_granted(lua, permissions, "mouse", Permission::Input, || {
         lua.create_table_from([
             {
-                let channel = Arc::clone(&channel);
+                let worker = Arc::clone(&worker);
                 (
                     "get_pos",
                     lua.create_function(move |_, ()| {
-                        let (sender, receiver) = ch::bounded(1);
-                        channel
-                            .send(LuaEvent::MouseGetPos { res: sender })
-                            .map_err(|e| {
-                                mlua::Error::RuntimeError(format!("Failed to send event: {}", e))
-                            })?;
-                        Ok(receiver.recv().unwrap())
+                        Ok(worker
+                            .request(|res| LuaEvent::MouseGetPos { res })?
+                            .unwrap_or_default())
+                    })?,
+                )
+            },
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "state",
+                    lua.create_function(move |lua, ()| {
+                        let Some(state) = worker.request(|res| LuaEvent::MouseState { res })?
+                        else {
+                            return Ok(Value::Nil);
+                        };
+
+                        let buttons = lua.create_table()?;
+                        for button in [
+                            Button::Left,
+                            Button::Right,
+                            Button::Middle,
+                            Button::Back,
+                            Button::Forward,
+                        ] {
+                            if let Some(index) = button.index() {
+                                let pressed =
+                                    state.button_pressed.get(index).copied().unwrap_or(false);
+                                buttons.set(button.name(), pressed)?;
+                            }
+                        }
+
+                        let table = lua.create_table()?;
+                        table.set("x", state.coords.0)?;
+                        table.set("y", state.coords.1)?;
+                        table.set("buttons", buttons)?;
+                        Ok(Value::Table(table))
                     })?,
                 )
             },
             {
-                let channel = Arc::clone(&channel);
+                let worker = Arc::clone(&worker);
                 (
                     "move",
                     lua.create_function(move |_, (x, y, coordinate): (i32, i32, String)| {
-                        channel
-                            .send(LuaEvent::MouseMove {
-                                x,
-                                y,
-                                coordinate: Coordinate::from_str(&coordinate).map_err(|_| {
-                                    mlua::Error::RuntimeError(format!(
-                                        "Invalid coordinate: {}",
-                                        coordinate
-                                    ))
-                                })?,
+                        worker.send(LuaEvent::MouseMove {
+                            x,
+                            y,
+                            coordinate: Coordinate::from_str(&coordinate).map_err(|_| {
+                                mlua::Error::RuntimeError(format!(
+                                    "Invalid coordinate: {}",
+                                    coordinate
+                                ))
+                            })?,
+                        })
+                    })?,
+                )
+            },
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "move_smooth",
+                    lua.create_function(move |_, (x, y, opts): (i32, i32, Option<Table>)| {
+                        let (duration, curve, coordinate) = match &opts {
+                            Some(opts) => (
+                                opts.get::<Option<u64>>("duration")?,
+                                opts.get::<Option<String>>("curve")?,
+                                opts.get::<Option<String>>("coord")?,
+                            ),
+                            None => (None, None, None),
+                        };
+                        let curve = curve.unwrap_or_else(|| "EaseInOut".to_string());
+                        let coordinate = coordinate.unwrap_or_else(|| "Abs".to_string());
+
+                        worker.send(LuaEvent::MouseMoveSmooth {
+                            x,
+                            y,
+                            coordinate: Coordinate::from_str(&coordinate).map_err(|_| {
+                                mlua::Error::RuntimeError(format!(
+                                    "Invalid coordinate: {}",
+                                    coordinate
+                                ))
+                            })?,
+                            duration: Duration::from_millis(duration.unwrap_or(300)),
+                            curve: Curve::from_str(&curve).map_err(|_| {
+                                mlua::Error::RuntimeError(format!("Invalid curve: {}", curve))
+                            })?,
+                        })
+                    })?,
+                )
+            },
+            declare_function!(
+                "click_at",
+                LuaEvent::MouseClickAt {
+                    x,
+                    y,
+                    button: optional_button(&button)?,
+                },
+                x: i32,
+                y: i32,
+                button: Value
+            ),
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "multi_click",
+                    lua.create_function(
+                        move |_, (count, interval, button): (u32, Option<u64>, Value)| {
+                            worker.send(LuaEvent::MouseMultiClick {
+                                button: optional_button(&button)?,
+                                count,
+                                interval: Duration::from_millis(
+                                    interval.unwrap_or(MULTI_CLICK_INTERVAL),
+                                ),
+                            })
+                        },
+                    )?,
+                )
+            },
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "double_click",
+                    lua.create_function(move |_, button: Value| {
+                        worker.send(LuaEvent::MouseMultiClick {
+                            button: optional_button(&button)?,
+                            count: 2,
+                            interval: Duration::from_millis(MULTI_CLICK_INTERVAL),
+                        })
+                    })?,
+                )
+            },
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "drag",
+                    lua.create_function(
+                        move |_, (from, to, opts): (Table, Table, Option<Table>)| {
+                            let (button, duration) = match &opts {
+                                Some(opts) => (
+                                    opts.get::<Value>("button")?,
+                                    opts.get::<Option<u64>>("duration")?,
+                                ),
+                                None => (Value::Nil, None),
+                            };
+
+                            worker.send(LuaEvent::MouseDrag {
+                                from: point(&from)?,
+                                to: point(&to)?,
+                                button: optional_button(&button)?,
+                                duration: Duration::from_millis(duration.unwrap_or(300)),
                             })
-                            .map_err(|e| {
-                                mlua::Error::RuntimeError(format!("Failed to send event: {}", e))
-                            })?;
-                        Ok(())
+                        },
+                    )?,
+                )
+            },
+            {
+                let worker = Arc::clone(&worker);
+                (
+                    "scroll",
+                    lua.create_function(move |_, (dx, dy, opts): (i32, i32, Option<Table>)| {
+                        let duration = match &opts {
+                            Some(opts) => opts.get::<Option<u64>>("duration")?.unwrap_or(0),
+                            None => 0,
+                        };
+
+                        worker.send(LuaEvent::MouseScroll {
+                            dx,
+                            dy,
+                            duration: Duration::from_millis(duration),
+                        })
                     })?,
                 )
             },
             declare_function!(
                 "press",
                 LuaEvent::MousePress {
-                    button: ButtonSend::from_str(&button).map_err(|_| {
-                        mlua::Error::RuntimeError(format!("Invalid button: {}", button))
-                    })?,
+                    button: send_button(&button)?,
                 },
-                button: String
+                button: Value
             ),
             declare_function!(
                 "release",
                 LuaEvent::MouseRelease {
-                    button: ButtonSend::from_str(&button).map_err(|_| {
-                        mlua::Error::RuntimeError(format!("Invalid button: {}", button))
-                    })?,
+                    button: send_button(&button)?,
                 },
-                button: String
+                button: Value
             ),
             declare_function!(
                 "click",
                 LuaEvent::MouseClick {
-                    button: ButtonSend::from_str(&button).map_err(|_| {
-                        mlua::Error::RuntimeError(format!("Invalid button: {}", button))
-                    })?,
+                    button: send_button(&button)?,
+                    humanize: HumanizeOptions::from_options(&opts)?,
                 },
-                button: String
+                button: Value,
+                opts: Option<Table>
             ),
             {
-                let channel = Arc::clone(&channel);
+                let worker = Arc::clone(&worker);
                 (
                     "is_pressing",
-                    lua.create_function(move |_, button: String| {
-                        let (sender, receiver) = ch::bounded(1);
-                        channel
-                            .send(LuaEvent::MouseIsPressing {
-                                button: u32::from_str(&button).map_err(|_| {
-                                    mlua::Error::RuntimeError(format!("Invalid button: {}", button))
-                                })?,
-                                res: sender,
-                            })
-                            .map_err(|e| {
-                                mlua::Error::RuntimeError(format!("Failed to send event: {}", e))
-                            })?;
-                        Ok(receiver.recv().unwrap())
+                    lua.create_function(move |_, button: Value| {
+                        let index = query_button(&button)?;
+                        Ok(worker
+                            .request(|res| LuaEvent::MouseIsPressing { index, res })?
+                            .unwrap_or(false))
                     })?,
                 )
             },
-        ])?,
+        ])
+    })?;
+
+    globals.set(
+        "humanize",
+        lua.create_table_from([(
+            "seed",
+            lua.create_function(move |_, seed: u64| {
+                *rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+                Ok(())
+            })?,
+        )])?,
     )?;
+    macro_rules! declare_dialog {
+        (
+            $name:expr, $ret:ty,
+            |$res:ident, $($arg:ident: $arg_type:ty),*| $event:expr
+        ) => {{
+            let host = host.clone();
+            let exit_flag = Arc::clone(&exit_flag);
+            (
+                $name,
+                lua.create_function(move |_, ($($arg,)*): ($($arg_type,)*)| {
+                    let ($res, receiver) = ch::bounded::<$ret>(1);
+                    host.send($event).map_err(|e| {
+                        mlua::Error::RuntimeError(format!("Failed to send event: {}", e))
+                    })?;
+                    wait_response(receiver, &exit_flag)
+                })?,
+            )
+        }};
+    }
 
     globals.set(
-        "sleep",
-        lua.create_function(move |_, ms: u64| {
-            std::thread::sleep(std::time::Duration::from_millis(ms));
-            Ok(())
-        })?,
+        "ui",
+        lua.create_table_from([
+            declare_dialog!(
+                "alert",
+                bool,
+                |res, message: String, title: Option<String>| HostEvent::Alert {
+                    title,
+                    message,
+                    res,
+                }
+            ),
+            declare_dialog!(
+                "confirm",
+                bool,
+                |res, message: String, title: Option<String>| HostEvent::Confirm {
+                    title,
+                    message,
+                    res,
+                }
+            ),
+            declare_dialog!(
+                "prompt",
+                Option<String>,
+                |res, message: String, default: Option<String>, title: Option<String>| {
+                    HostEvent::Prompt {
+                        title,
+                        message,
+                        default: default.unwrap_or_default(),
+                        res,
+                    }
+                }
+            ),
+            granted_function(
+                lua,
+                permissions,
+                "ui.pick_file",
+                Permission::Filesystem,
+                || {
+                    Ok(declare_dialog!(
+                        "pick_file",
+                        Option<String>,
+                        |res, opts: Option<Table>| {
+                            let (title, directory) = dialog_options(&opts)?;
+                            let mut filters = Vec::new();
+                            if let Some(list) = opts
+                                .as_ref()
+                                .map(|opts| opts.get::<Option<Table>>("filters"))
+                                .transpose()?
+                                .flatten()
+                            {
+                                for filter in list.sequence_values::<Table>() {
+                                    let filter = filter?;
+                                    filters.push((
+                                        filter.get::<String>("name")?,
+                                        filter.get::<Vec<String>>("extensions")?,
+                                    ));
+                                }
+                            }
+
+                            HostEvent::PickFile {
+                                title,
+                                directory,
+                                filters,
+                                res,
+                            }
+                        }
+                    ))
+                },
+            )?,
+            granted_function(
+                lua,
+                permissions,
+                "ui.pick_folder",
+                Permission::Filesystem,
+                || {
+                    Ok(declare_dialog!(
+                        "pick_folder",
+                        Option<String>,
+                        |res, opts: Option<Table>| {
+                            let (title, directory) = dialog_options(&opts)?;
+
+                            HostEvent::PickFolder {
+                                title,
+                                directory,
+                                res,
+                            }
+                        }
+                    ))
+                },
+            )?,
+        ])?,
     )?;
 
+    set_granted(lua, permissions, "macro", Permission::Input, || {
+        lua.create_table_from([{
+            let worker = Arc::clone(&worker);
+            let script_dir = script_dir.to_path_buf();
+            (
+                "play",
+                lua.create_function(move |_, (path, opts): (String, Option<Table>)| {
+                    let path = path.try_resolve_in(&script_dir)?;
+                    let timeline = MacroTimeline::load(&path).map_err(|e| {
+                        mlua::Error::RuntimeError(format!(
+                            "Failed to load macro {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?;
+                    let (speed, loops) = match opts {
+                        Some(opts) => (
+                            opts.get::<Option<f64>>("speed")?.unwrap_or(1.0),
+                            opts.get::<Option<u32>>("loops")?.unwrap_or(1),
+                        ),
+                        None => (1.0, 1),
+                    };
+
+                    worker.send(LuaEvent::MacroPlay {
+                        timeline,
+                        speed,
+                        loops,
+                    })
+                })?,
+            )
+        }])
+    })?;
+
+    set_granted(lua, permissions, "notify", Permission::Notify, || {
+        lua.create_function(
+            move |_, (title, body, opts): (String, Option<String>, Option<Table>)| {
+                host.send(HostEvent::Notify {
+                    title,
+                    body: body.unwrap_or_default(),
+                    icon: opts
+                        .map(|opts| opts.get::<Option<String>>("icon"))
+                        .transpose()?
+                        .flatten(),
+                })
+                .map_err(|e| mlua::Error::RuntimeError(format!("Failed to send event: {}", e)))?;
+                Ok(())
+            },
+        )
+    })?;
+
+    set_granted(lua, permissions, "input", Permission::Input, || {
+        lua.create_table_from([
+            (
+                "idle_ms",
+                lua.create_function(|_, ()| Ok(input::idle_time().as_millis() as u64))?,
+            ),
+            (
+                "token",
+                lua.create_function(|_, ()| Ok(input::edges().token()))?,
+            ),
+        ])
+    })?;
+
+    if permissions.contains(&Permission::Input) {
+        let subscriptions = Arc::new(Mutex::new(HashMap::new()));
+        for (module, to_input) in [
+            ("keyboard", key_input as InputParser),
+            ("mouse", button_input),
+        ] {
+            let table: Table = globals.get(module)?;
+            for (name, function) in edge_functions(lua, &subscriptions, to_input)? {
+                table.set(name, function)?;
+            }
+        }
+    }
+
     lua.load(fs::read(format!("{}/post.lua", std_path))?)
         .exec()
         .context("Failed to load post.lua")?;
 
     Ok(())
 }
+
+// 一時停止している間は時間を進めない
+pub(super) fn register_timing(
+    lua: &Lua,
+    rng: Arc<Mutex<StdRng>>,
+    exit_flag: Arc<AtomicBool>,
+    pause: Arc<PauseState>,
+) -> mlua::Result<()> {
+    let globals = lua.globals();
+    let clock = Arc::new(Clock::new(Arc::clone(&pause)));
+
+    globals.set("sleep", {
+        let exit_flag = Arc::clone(&exit_flag);
+        lua.create_function(move |_, (ms, jitter): (u64, Option<u64>)| {
+            let duration = match jitter {
+                Some(jitter) => humanize::jitter(&mut rng.lock().unwrap(), ms, jitter),
+                None => Duration::from_millis(ms),
+            };
+            pause.sleep(duration, &exit_flag);
+            Ok(())
+        })?
+    })?;
+    globals.set("sleep_precise", {
+        let clock = Arc::clone(&clock);
+        let exit_flag = Arc::clone(&exit_flag);
+        lua.create_function(move |_, ms: f64| {
+            let duration = clock::duration_from_ms(ms)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid duration: {}", ms)))?;
+            clock.sleep(duration, &exit_flag);
+            Ok(())
+        })?
+    })?;
+    globals.set("every", {
+        let clock = Arc::clone(&clock);
+        lua.create_function(move |_, (hz, f): (f64, Function)| {
+            let period = clock::period_from_hz(hz)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid rate: {}", hz)))?;
+            let mut next = clock.now();
+            for tick in 1u64.. {
+                if let Value::Boolean(false) = f.call::<Value>(tick)? {
+                    break;
+                }
+                next = clock::next_tick(next, clock.now(), period);
+                if !clock.sleep_until(next, &exit_flag) {
+                    break;
+                }
+            }
+            Ok(())
+        })?
+    })?;
+
+    let time = lua.create_table()?;
+    time.set("now", {
+        let clock = Arc::clone(&clock);
+        lua.create_function(move |_, ()| Ok(clock.now().as_secs_f64() * 1000.0))?
+    })?;
+    time.set(
+        "since",
+        lua.create_function(move |_, t: f64| Ok(clock.now().as_secs_f64() * 1000.0 - t))?,
+    )?;
+    globals.set("time", time)?;
+
+    Ok(())
+}
+
+// require と package はスクリプトの隣のファイルを読めるので、Filesystem がなければ読み込まない
+pub(super) fn std_libs(permissions: &BTreeSet<Permission>) -> StdLib {
+    if permissions.contains(&Permission::Filesystem) {
+        StdLib::ALL
+    } else {
+        StdLib::ALL ^ StdLib::PACKAGE
+    }
+}
+
+// 許可されていなければ作らずに、使うとエラーになるモジュールを置く
+fn set_granted<V: IntoLua>(
+    lua: &Lua,
+    permissions: &BTreeSet<Permission>,
+    name: &'static str,
+    permission: Permission,
+    create: impl FnOnce() -> mlua::Result<V>,
+) -> mlua::Result<()> {
+    let value = if permissions.contains(&permission) {
+        create()?.into_lua(lua)?
+    } else {
+        Value::Table(denied_module(lua, name, permission)?)
+    };
+    lua.globals().set(name, value)
+}
+
+// モジュールの中の一部の関数だけに権限が要るとき。name は "ui.pick_file" のように書く
+fn granted_function(
+    lua: &Lua,
+    permissions: &BTreeSet<Permission>,
+    name: &'static str,
+    permission: Permission,
+    create: impl FnOnce() -> mlua::Result<(&'static str, Function)>,
+) -> mlua::Result<(&'static str, Function)> {
+    if permissions.contains(&permission) {
+        create()
+    } else {
+        let key = name.rsplit('.').next().unwrap_or(name);
+        Ok((key, denied_function(lua, name, permission)?))
+    }
+}
+
+fn denied_function(
+    lua: &Lua,
+    name: &'static str,
+    permission: Permission,
+) -> mlua::Result<Function> {
+    lua.create_function(move |_, _: mlua::MultiValue| {
+        Err::<(), _>(mlua::Error::external(PermissionDenied {
+            module: name,
+            permission,
+        }))
+    })
+}
+
+fn denied_module(lua: &Lua, name: &'static str, permission: Permission) -> mlua::Result<Table> {
+    let denied = denied_function(lua, name, permission)?;
+
+    let module = lua.create_table()?;
+    module.set_metatable(Some(
+        lua.create_table_from([("__index", denied.clone()), ("__call", denied)])?,
+    ));
+    Ok(module)
+}
+
+fn dialog_options(opts: &Option<Table>) -> mlua::Result<(Option<String>, Option<String>)> {
+    match opts {
+        Some(opts) => Ok((opts.get("title")?, opts.get("directory")?)),
+        None => Ok((None, None)),
+    }
+}
+
+fn parse_button(value: &Value) -> mlua::Result<Button> {
+    let button = match value {
+        Value::Integer(number) => usize::try_from(*number).ok().and_then(Button::from_number),
+        Value::Number(number) if number.fract() == 0.0 && *number >= 0.0 => {
+            Button::from_number(*number as usize)
+        }
+        Value::String(name) => {
+            let name = name.to_str()?;
+            Button::from_str(&name)
+                .ok()
+                .or_else(|| name.parse().ok().and_then(Button::from_number))
+        }
+        _ => None,
+    };
+    button.ok_or_else(|| {
+        mlua::Error::RuntimeError(format!(
+            "Invalid button: {}",
+            value
+                .to_string()
+                .unwrap_or_else(|_| value.type_name().to_string())
+        ))
+    })
+}
+
+fn send_button(value: &Value) -> mlua::Result<enigo::Button> {
+    let button = parse_button(value)?;
+    button.send().ok_or_else(|| {
+        mlua::Error::RuntimeError(format!(
+            "The {} button cannot be sent on {}",
+            button.name(),
+            std::env::consts::OS
+        ))
+    })
+}
+
+fn query_key(key: &str) -> mlua::Result<Keycode> {
+    Key::from_str(key)
+        .map_err(|_| mlua::Error::RuntimeError(format!("Invalid key: {}", key)))?
+        .keycode()
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("Key cannot be queried: {}", key)))
+}
+
+fn query_button(value: &Value) -> mlua::Result<usize> {
+    let button = parse_button(value)?;
+    button.index().ok_or_else(|| {
+        mlua::Error::RuntimeError(format!(
+            "The {} button cannot be queried on {}",
+            button.name(),
+            std::env::consts::OS
+        ))
+    })
+}
+
+type Subscriptions = Arc<Mutex<HashMap<String, Subscription>>>;
+type InputParser = fn(Value, &Lua) -> mlua::Result<Input>;
+
+fn key_input(value: Value, lua: &Lua) -> mlua::Result<Input> {
+    Ok(Input::Key(query_key(&String::from_lua(value, lua)?)?))
+}
+
+fn button_input(value: Value, _: &Lua) -> mlua::Result<Input> {
+    Ok(Input::Button(query_button(&value)?))
+}
+
+fn edge_functions(
+    lua: &Lua,
+    subscriptions: &Subscriptions,
+    to_input: InputParser,
+) -> mlua::Result<[(&'static str, Function); 3]> {
+    let edge = |just_pressed: bool| {
+        let subscriptions = Arc::clone(subscriptions);
+        lua.create_function(move |lua, (input, id): (Value, Option<String>)| {
+            let input = to_input(input, lua)?;
+            // idを省略した場合は呼び出し元の位置ごとに購読する
+            let id = match id {
+                Some(id) => id,
+                None => lua
+                    .inspect_stack(1)
+                    .map(|debug| {
+                        format!(
+                            "{}:{}",
+                            debug.source().short_src.unwrap_or_default(),
+                            debug.curr_line()
+                        )
+                    })
+                    .unwrap_or_default(),
+            };
+
+            let mut subscriptions = subscriptions.lock().unwrap();
+            let subscription = subscriptions
+                .entry(id)
+                .or_insert_with(|| input::edges().subscribe());
+            Ok(if just_pressed {
+                subscription.just_pressed(input)
+            } else {
+                subscription.just_released(input)
+            })
+        })
+    };
+
+    Ok([
+        ("just_pressed", edge(true)?),
+        ("just_released", edge(false)?),
+        (
+            "was_pressed_since",
+            lua.create_function(move |lua, (input, token): (Value, u64)| {
+                Ok(input::edges().was_pressed_since(to_input(input, lua)?, token))
+            })?,
+        ),
+    ])
+}
+
+fn optional_button(value: &Value) -> mlua::Result<enigo::Button> {
+    match value {
+        Value::Nil => Ok(enigo::Button::Left),
+        value => send_button(value),
+    }
+}
+
+fn point(table: &Table) -> mlua::Result<(i32, i32)> {
+    match (
+        table.get::<Option<i32>>("x")?,
+        table.get::<Option<i32>>("y")?,
+    ) {
+        (Some(x), Some(y)) => Ok((x, y)),
+        _ => Ok((table.get::<i32>(1)?, table.get::<i32>(2)?)),
+    }
+}
+
+fn single_char(text: &str) -> mlua::Result<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Ok(char),
+        _ => Err(mlua::Error::RuntimeError(format!(
+            "Invalid character: {:?} (use keyboard.type for strings)",
+            text
+        ))),
+    }
+}
+
+fn wait_response<T>(receiver: ch::Receiver<T>, exit_flag: &AtomicBool) -> mlua::Result<Option<T>> {
+    loop {
+        if exit_flag.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        match receiver.recv_timeout(Duration::from_millis(50)) {
+            Ok(value) => return Ok(Some(value)),
+            Err(ch::RecvTimeoutError::Timeout) => continue,
+            Err(e) => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "Failed to receive response: {}",
+                    e
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::pause::PauseState;
+
+// None は無制限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    pub memory_mb: Option<usize>,
+    // Luau の割り込みはループや関数呼び出しごとに発生するので、その回数で数える
+    pub instructions_per_sec: Option<u64>,
+    pub max_runtime_secs: Option<u64>,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            memory_mb: Some(512),
+            instructions_per_sec: None,
+            max_runtime_secs: None,
+        }
+    }
+}
+impl Limits {
+    // スクリプト側で指定された値を優先する
+    pub fn or(self, global: Limits) -> Limits {
+        Limits {
+            memory_mb: self.memory_mb.or(global.memory_mb),
+            instructions_per_sec: self.instructions_per_sec.or(global.instructions_per_sec),
+            max_runtime_secs: self.max_runtime_secs.or(global.max_runtime_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum LimitExceeded {
+    #[error("limit exceeded: memory ({0} MB)")]
+    Memory(usize),
+    #[error("limit exceeded: instructions per second ({0})")]
+    Instructions(u64),
+    #[error("limit exceeded: runtime ({0} s)")]
+    Runtime(u64),
+    #[error("limit exceeded: input flood (more than {0} s of input queued)")]
+    InputFlood(u64),
+}
+
+pub struct Budget {
+    limits: Limits,
+    pause: Arc<PauseState>,
+    // 開始時刻と、その時点までに一時停止していた時間
+    started: Mutex<(Instant, Duration)>,
+    window: Mutex<(Instant, u64)>,
+    exceeded: Mutex<Option<LimitExceeded>>,
+}
+impl Budget {
+    pub fn new(limits: Limits, pause: Arc<PauseState>) -> Self {
+        let now = Instant::now();
+        Budget {
+            limits,
+            started: Mutex::new((now, pause.total())),
+            pause,
+            window: Mutex::new((now, 0)),
+            exceeded: Mutex::new(None),
+        }
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+    pub fn restart(&self) {
+        let now = Instant::now();
+        *self.started.lock().unwrap() = (now, self.pause.total());
+        *self.window.lock().unwrap() = (now, 0);
+        *self.exceeded.lock().unwrap() = None;
+    }
+    // 一時停止していた時間の分だけ延びる
+    pub fn deadline(&self) -> Option<Instant> {
+        self.limits.max_runtime_secs.map(|secs| {
+            let (started, paused) = *self.started.lock().unwrap();
+            started + Duration::from_secs(secs) + (self.pause.total() - paused)
+        })
+    }
+
+    // 割り込みごとに呼ぶ
+    pub fn tick(&self) -> Result<(), LimitExceeded> {
+        let now = Instant::now();
+        if let (Some(secs), Some(deadline)) = (self.limits.max_runtime_secs, self.deadline()) {
+            if now >= deadline {
+                return Err(self.exceed(LimitExceeded::Runtime(secs)));
+            }
+        }
+        if let Some(limit) = self.limits.instructions_per_sec {
+            let mut window = self.window.lock().unwrap();
+            if now - window.0 >= Duration::from_secs(1) {
+                *window = (now, 0);
+            }
+            window.1 += 1;
+            if window.1 > limit {
+                return Err(self.exceed(LimitExceeded::Instructions(limit)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn exceed(&self, exceeded: LimitExceeded) -> LimitExceeded {
+        *self.exceeded.lock().unwrap().get_or_insert(exceeded)
+    }
+    pub fn exceeded(&self) -> Option<LimitExceeded> {
+        *self.exceeded.lock().unwrap()
+    }
+
+    // メモリ上限は mlua のエラーとして返ってくるので、上限超過のエラーに置き換える
+    pub fn map_error(&self, err: mlua::Error) -> mlua::Error {
+        match self.limits.memory_mb {
+            Some(memory_mb) if is_memory_error(&err) => {
+                mlua::Error::external(self.exceed(LimitExceeded::Memory(memory_mb)))
+            }
+            _ => err,
+        }
+    }
+}
+
+fn is_memory_error(err: &mlua::Error) -> bool {
+    match err {
+        mlua::Error::MemoryError(_) => true,
+        mlua::Error::CallbackError { cause, .. } => is_memory_error(cause),
+        mlua::Error::WithContext { cause, .. } => is_memory_error(cause),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_limits(limits: Limits) -> Budget {
+        Budget::new(limits, Arc::new(PauseState::default()))
+    }
+
+    #[test]
+    fn instruction_window_resets() {
+        let budget = with_limits(Limits {
+            instructions_per_sec: Some(3),
+            ..Limits::default()
+        });
+        for _ in 0..3 {
+            assert_eq!(budget.tick(), Ok(()));
+        }
+        assert_eq!(budget.tick(), Err(LimitExceeded::Instructions(3)));
+
+        // 1 秒経てば数え直す
+        budget.window.lock().unwrap().0 -= Duration::from_secs(1);
+        for _ in 0..3 {
+            assert_eq!(budget.tick(), Ok(()));
+        }
+        assert_eq!(budget.tick(), Err(LimitExceeded::Instructions(3)));
+    }
+
+    #[test]
+    fn runtime_deadline_trips() {
+        let budget = with_limits(Limits {
+            max_runtime_secs: Some(5),
+            ..Limits::default()
+        });
+        assert_eq!(budget.tick(), Ok(()));
+        assert_eq!(budget.exceeded(), None);
+
+        budget.started.lock().unwrap().0 -= Duration::from_secs(5);
+        assert_eq!(budget.tick(), Err(LimitExceeded::Runtime(5)));
+        assert_eq!(budget.exceeded(), Some(LimitExceeded::Runtime(5)));
+
+        budget.restart();
+        assert_eq!(budget.exceeded(), None);
+        assert_eq!(budget.tick(), Ok(()));
+        assert_eq!(with_limits(Limits::default()).deadline(), None);
+    }
+
+    #[test]
+    fn maps_memory_error() {
+        let lua = mlua::Lua::new();
+        lua.set_memory_limit(1024 * 1024).unwrap();
+        let err = lua
+            .load("local s = string.rep('x', 4 * 1024 * 1024)")
+            .exec()
+            .unwrap_err();
+
+        let budget = with_limits(Limits {
+            memory_mb: Some(1),
+            ..Limits::default()
+        });
+        let mlua::Error::ExternalError(mapped) = budget.map_error(err) else {
+            panic!("memory error was not mapped");
+        };
+        assert_eq!(
+            mapped.downcast_ref::<LimitExceeded>(),
+            Some(&LimitExceeded::Memory(1))
+        );
+        assert_eq!(budget.exceeded(), Some(LimitExceeded::Memory(1)));
+
+        // 組み込み関数の中で起きても置き換える
+        let nested = mlua::Error::CallbackError {
+            traceback: String::new(),
+            cause: Arc::new(mlua::Error::MemoryError("not enough memory".to_string())),
+        };
+        assert!(matches!(
+            budget.map_error(nested),
+            mlua::Error::ExternalError(_)
+        ));
+
+        // 上限がなければ、それ以外のエラーと同じくそのまま返す
+        let unlimited = with_limits(Limits {
+            memory_mb: None,
+            ..Limits::default()
+        });
+        let err = unlimited.map_error(mlua::Error::MemoryError("not enough memory".to_string()));
+        assert!(matches!(err, mlua::Error::MemoryError(_)));
+        let err = budget.map_error(mlua::Error::RuntimeError("boom".to_string()));
+        assert!(matches!(err, mlua::Error::RuntimeError(_)));
+    }
+}
@@ -1,59 +1,105 @@
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
-    thread::{spawn, JoinHandle},
+    thread::{sleep, spawn, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use super::LuaInstance;
+use crossbeam::channel as ch;
+
+use super::{HostEvent, LuaInstance, ScriptError, ScriptOptions};
+
+const STOP_POLL: Duration = Duration::from_millis(20);
 
 pub struct LuaManager {
+    host: ch::Sender<HostEvent>,
+    // 停止を要求してから切り離すまでの猶予
+    stop_timeout: Duration,
     current: Option<Arc<LuaInstance>>,
     current_thread: Option<JoinHandle<()>>,
+    current_path: Option<PathBuf>,
 }
 impl LuaManager {
-    pub fn new() -> Self {
+    pub fn new(host: ch::Sender<HostEvent>, stop_timeout: Duration) -> Self {
         LuaManager {
+            host,
+            stop_timeout,
             current: None,
             current_thread: None,
+            current_path: None,
         }
     }
     pub fn execute_from_file<FP: AsRef<Path>, SP: AsRef<Path>, F>(
         &mut self,
         file_path: FP,
         std_path: SP,
+        options: &ScriptOptions,
         f: F,
     ) -> anyhow::Result<()>
     where
-        F: FnOnce(mlua::Error) + Send + 'static,
+        F: FnOnce(Result<(), ScriptError>) + Send + 'static,
     {
         self.stop_current()?;
 
-        let instance = Arc::new(LuaInstance::create_from_file(file_path, std_path)?);
+        let path = file_path.as_ref().to_path_buf();
+        let instance = Arc::new(LuaInstance::create_from_file(
+            file_path,
+            std_path,
+            options,
+            self.host.clone(),
+        )?);
         self.current = Some(Arc::clone(&instance));
-        self.current_thread = Some(spawn(move || {
-            if let Err(err) = instance.execute() {
-                if let mlua::Error::CallbackError { cause, .. } = &err {
-                    if let mlua::Error::RuntimeError(cause) = &**cause {
-                        if cause == "interrupted" {
-                            return;
-                        }
-                    }
-                }
-
-                f(err);
-            };
+        self.current_path = Some(path);
+        self.current_thread = Some(spawn(move || match instance.execute() {
+            // ユーザーが止めた場合は知らせない
+            Err(ScriptError::Interrupted) => {}
+            res => f(res),
         }));
 
         Ok(())
     }
+    // 実行中でなければ None。一時停止したら Some(true)、再開したら Some(false)
+    pub fn toggle_pause(&self) -> Option<bool> {
+        let curr = self.current.as_ref()?;
+        if self
+            .current_thread
+            .as_ref()
+            .is_none_or(|handle| handle.is_finished())
+        {
+            return None;
+        }
+        if curr.is_paused() {
+            curr.resume();
+            Some(false)
+        } else {
+            curr.pause();
+            Some(true)
+        }
+    }
+    // 終了を待たずに戻る。猶予を過ぎても終わらなければ切り離す
     pub fn stop_current(&mut self) -> anyhow::Result<()> {
-        if let Some(curr) = &self.current.take() {
+        let path = self.current_path.take().unwrap_or_default();
+        if let Some(curr) = self.current.take() {
             curr.stop()?;
 
             if let Some(handle) = self.current_thread.take() {
-                handle
-                    .join()
-                    .map_err(|_| anyhow::anyhow!("Failed to join Lua thread"))?;
+                let host = self.host.clone();
+                let timeout = self.stop_timeout;
+                spawn(move || {
+                    let deadline = Instant::now() + timeout;
+                    while !handle.is_finished() {
+                        if Instant::now() >= deadline {
+                            // 組み込み関数の中で止まっているので、スレッドは放置して入力だけ片付ける
+                            curr.abandon();
+                            let _ = host.send(HostEvent::ScriptStuck { path, timeout });
+                            return;
+                        }
+                        sleep(STOP_POLL);
+                    }
+                    if handle.join().is_err() {
+                        eprintln!("Lua thread panicked: {}", path.display());
+                    }
+                });
             }
         }
 
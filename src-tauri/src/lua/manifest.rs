@@ -0,0 +1,260 @@
+use std::{collections::BTreeSet, fs, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use strum::IntoEnumIterator;
+
+use super::Limits;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    strum::EnumString,
+    strum::IntoStaticStr,
+    strum::VariantNames,
+    strum::EnumIter,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Permission {
+    // keyboard, mouse, input, macro
+    Input,
+    // ui.pick_file, ui.pick_folder, スクリプトの隣のファイルの require
+    Filesystem,
+    // notify
+    Notify,
+}
+impl Permission {
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+}
+
+// 予定している権限。使える組み込み関数がまだないので、書かれていても何も許可しない
+const RESERVED_PERMISSIONS: &[&str] = &["screen", "clipboard", "process", "network"];
+
+// ユーザーが操作したときにどうするか
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum::EnumString,
+    strum::VariantNames,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum UserInputPolicy {
+    #[default]
+    Ignore,
+    Pause,
+    Stop,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestError {
+    #[error("Missing `--[[ title ]]` header on the first line")]
+    MissingTitle,
+    #[error("Unknown permission at line {line}: {name}")]
+    UnknownPermission { line: usize, name: String },
+    #[error("Invalid value for {key} at line {line}: {value}")]
+    InvalidValue {
+        line: usize,
+        key: String,
+        value: String,
+    },
+}
+
+// スクリプト先頭のコメント
+//
+// --[[ タイトル ]]
+// -- permissions: input, notify
+// -- max_runtime_secs: 600
+// -- on_user_input: stop
+//
+// permissions がないスクリプトは、以前と同じくすべての権限を要求する
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub title: String,
+    pub permissions: BTreeSet<Permission>,
+    pub permissions_declared: bool,
+    // 指定されていない項目は None
+    pub limits: Limits,
+    pub on_user_input: Option<UserInputPolicy>,
+    pub hash: String,
+}
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?)?)
+    }
+    pub fn parse(content: &str) -> Result<Self, ManifestError> {
+        let mut lines = content.lines();
+        let title = lines
+            .next()
+            .and_then(|line| line.split_once("--[["))
+            .and_then(|(_, header)| header.split_once("]]"))
+            .map(|(header, _)| header.trim().to_string())
+            .ok_or(ManifestError::MissingTitle)?;
+
+        let mut permissions = BTreeSet::new();
+        let mut permissions_declared = false;
+        let mut limits = Limits {
+            memory_mb: None,
+            instructions_per_sec: None,
+            max_runtime_secs: None,
+        };
+        let mut on_user_input = None;
+        for (index, line) in lines.enumerate() {
+            let line_number = index + 2;
+            let Some(comment) = line.trim().strip_prefix("--") else {
+                break;
+            };
+            let Some((key, value)) = comment.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || ManifestError::InvalidValue {
+                line: line_number,
+                key: key.to_string(),
+                value: value.to_string(),
+            };
+
+            match key {
+                "permissions" => {
+                    permissions_declared = true;
+                    for name in value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                    {
+                        let lower = name.to_ascii_lowercase();
+                        if RESERVED_PERMISSIONS.contains(&lower.as_str()) {
+                            continue;
+                        }
+                        let permission = Permission::from_str(&lower).map_err(|_| {
+                            ManifestError::UnknownPermission {
+                                line: line_number,
+                                name: name.to_string(),
+                            }
+                        })?;
+                        permissions.insert(permission);
+                    }
+                }
+                "memory_mb" => limits.memory_mb = Some(value.parse().map_err(|_| invalid())?),
+                "instructions_per_sec" => {
+                    limits.instructions_per_sec = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "max_runtime_secs" => {
+                    limits.max_runtime_secs = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "on_user_input" => {
+                    on_user_input = Some(
+                        UserInputPolicy::from_str(&value.to_ascii_lowercase())
+                            .map_err(|_| invalid())?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        if !permissions_declared {
+            permissions = Permission::iter().collect();
+        }
+
+        Ok(Manifest {
+            title,
+            permissions,
+            permissions_declared,
+            limits,
+            on_user_input,
+            hash: format!("{:x}", Sha256::digest(content.as_bytes())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_permissions() {
+        let manifest = Manifest::parse(
+            "--[[ Test ]]\n-- permissions: input, Notify\n-- permissions: filesystem\nMain = nil\n-- permissions: process",
+        )
+        .unwrap();
+        assert_eq!(manifest.title, "Test");
+        assert_eq!(
+            manifest.permissions,
+            BTreeSet::from([
+                Permission::Input,
+                Permission::Filesystem,
+                Permission::Notify
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_limits() {
+        let manifest =
+            Manifest::parse("--[[ Test ]]\n-- memory_mb: 64\n-- max_runtime_secs: 10").unwrap();
+        assert_eq!(manifest.limits.memory_mb, Some(64));
+        assert_eq!(manifest.limits.instructions_per_sec, None);
+        assert_eq!(manifest.limits.max_runtime_secs, Some(10));
+        assert_eq!(manifest.on_user_input, None);
+        assert_eq!(
+            Manifest::parse("--[[ Test ]]\n-- on_user_input: Pause")
+                .unwrap()
+                .on_user_input,
+            Some(UserInputPolicy::Pause)
+        );
+        assert!(matches!(
+            Manifest::parse("--[[ Test ]]\n-- memory_mb: lots"),
+            Err(ManifestError::InvalidValue { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn accepts_reserved_permissions() {
+        let manifest =
+            Manifest::parse("--[[ Test ]]\n-- permissions: input, Network, clipboard").unwrap();
+        assert_eq!(manifest.permissions, BTreeSet::from([Permission::Input]));
+
+        let manifest = Manifest::parse("--[[ Test ]]\n-- permissions: screen").unwrap();
+        assert!(manifest.permissions.is_empty());
+        assert!(manifest.permissions_declared);
+    }
+
+    #[test]
+    fn missing_permissions_request_everything() {
+        let manifest = Manifest::parse("--[[ Test ]]\n-- max_runtime_secs: 10").unwrap();
+        assert!(!manifest.permissions_declared);
+        assert_eq!(manifest.permissions, Permission::iter().collect());
+
+        let manifest = Manifest::parse("--[[ Test ]]\n-- permissions:").unwrap();
+        assert!(manifest.permissions_declared);
+        assert!(manifest.permissions.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_permission() {
+        assert!(matches!(
+            Manifest::parse("--[[ Test ]]\n-- permissions: input, root"),
+            Err(ManifestError::UnknownPermission { line: 2, .. })
+        ));
+        assert!(matches!(
+            Manifest::parse("print(1)"),
+            Err(ManifestError::MissingTitle)
+        ));
+    }
+}
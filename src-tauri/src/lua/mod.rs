@@ -1,6 +1,35 @@
+mod api;
+mod clock;
+mod edge;
+mod error;
+mod gesture;
+mod host;
+mod humanize;
+mod input;
 mod instance;
+mod limits;
 mod manager;
+mod manifest;
 mod model;
+mod output;
+mod pause;
+mod report;
+mod sequence;
+mod throttle;
+mod timeline;
+mod typing;
+mod worker;
 
+pub use api::write_meta;
+pub use error::ScriptError;
+pub use host::*;
+pub use input::{on_hotkey, set_failsafe, start_input_poller, Corner, Screen};
 pub use instance::*;
+pub use limits::{LimitExceeded, Limits};
 pub use manager::*;
+pub use manifest::{Manifest, Permission, UserInputPolicy};
+pub use model::{Button, Key};
+pub use output::Output;
+pub use report::ErrorReport;
+pub use throttle::RateLimits;
+pub use timeline::*;
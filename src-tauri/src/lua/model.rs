@@ -1,3 +1,8 @@
+use std::str::FromStr;
+
+use device_query::Keycode;
+use strum::IntoEnumIterator;
+
 macro_rules! define_enum_with_into {
     (
         $(#[$meta:meta])*
@@ -10,7 +15,7 @@ macro_rules! define_enum_with_into {
         => $Target:ty
     ) => {
         $(#[$meta])*
-        #[derive(strum::EnumString)]
+        #[derive(strum::EnumString, strum::VariantNames)]
         $vis enum $Name { $( $(#[$var_meta])* $Variant ),* }
 
         impl From<$Name> for $Target {
@@ -22,6 +27,42 @@ macro_rules! define_enum_with_into {
     };
 }
 
+macro_rules! define_keys {
+    (
+        $(
+            $(#[$var_meta:meta])*
+            $Variant:ident $(($Query:ident))? => $Send:expr
+        ),* $(,)?
+    ) => {
+        #[derive(
+            Debug,
+            Clone,
+            Copy,
+            PartialEq,
+            Eq,
+            strum::VariantNames,
+            strum::IntoStaticStr,
+            strum::EnumIter,
+        )]
+        pub enum Key { $( $(#[$var_meta])* $Variant ),* }
+
+        impl From<Key> for enigo::Key {
+            #[inline(always)]
+            fn from(v: Key) -> Self {
+                match v { $( $(#[$var_meta])* Key::$Variant => $Send ),* }
+            }
+        }
+
+        impl Key {
+            pub fn keycode(self) -> Option<Keycode> {
+                match self { $( $(#[$var_meta])* Key::$Variant => define_keys!(@query $($Query)?) ),* }
+            }
+        }
+    };
+    (@query) => { None };
+    (@query $Query:ident) => { Some(Keycode::$Query) };
+}
+
 define_enum_with_into! {
     pub enum Direction {
         Press => enigo::Direction::Press,
@@ -30,92 +71,248 @@ define_enum_with_into! {
     } => enigo::Direction
 }
 
-define_enum_with_into! {
-    pub enum KeySend {
-        F1 => enigo::Key::F1,
-        F2 => enigo::Key::F2,
-        F3 => enigo::Key::F3,
-        F4 => enigo::Key::F4,
-        F5 => enigo::Key::F5,
-        F6 => enigo::Key::F6,
-        F7 => enigo::Key::F7,
-        F8 => enigo::Key::F8,
-        F9 => enigo::Key::F9,
-        F10 => enigo::Key::F10,
-        F11 => enigo::Key::F11,
-        F12 => enigo::Key::F12,
-        F13 => enigo::Key::F13,
-        F14 => enigo::Key::F14,
-        F15 => enigo::Key::F15,
-        F16 => enigo::Key::F16,
-        F17 => enigo::Key::F17,
-        F18 => enigo::Key::F18,
-        F19 => enigo::Key::F19,
-        F20 => enigo::Key::F20,
-        LShift => enigo::Key::LShift,
-        RShift => enigo::Key::RShift,
-        LCtrl => enigo::Key::LControl,
-        RCtrl => enigo::Key::RControl,
-        Tab => enigo::Key::Tab,
-        Space => enigo::Key::Space,
-
-        #[cfg(target_os = "macos")] LCommand => enigo::Key::Meta,
-        #[cfg(target_os = "windows")] LCommand => enigo::Key::LWin,
-        #[cfg(target_os = "macos")] RCommand => enigo::Key::Other(0x36),
-        #[cfg(target_os = "windows")] RCommand => enigo::Key::RWin,
-
-        #[cfg(target_os = "macos")] Option => enigo::Key::Option,
-        #[cfg(target_os = "windows")] Option => enigo::Key::Alt,
-
-        CapsLock => enigo::Key::CapsLock,
-        Enter => enigo::Key::Return,
-        Esc => enigo::Key::Escape,
-        Backspace => enigo::Key::Backspace,
-        Delete => enigo::Key::Delete,
-
-        Up => enigo::Key::UpArrow,
-        Down => enigo::Key::DownArrow,
-        Left => enigo::Key::LeftArrow,
-        Right => enigo::Key::RightArrow,
-
-        Home => enigo::Key::Home,
-        End => enigo::Key::End,
-        PageUp => enigo::Key::PageUp,
-        PageDown => enigo::Key::PageDown,
-
-        #[cfg(target_os = "macos")] Numpad0 => enigo::Key::Other(82),
-        #[cfg(target_os = "windows")] Numpad0 => enigo::Key::Numpad0,
-        #[cfg(target_os = "macos")] Numpad1 => enigo::Key::Other(83),
-        #[cfg(target_os = "windows")] Numpad1 => enigo::Key::Numpad1,
-        #[cfg(target_os = "macos")] Numpad2 => enigo::Key::Other(84),
-        #[cfg(target_os = "windows")] Numpad2 => enigo::Key::Numpad2,
-        #[cfg(target_os = "macos")] Numpad3 => enigo::Key::Other(85),
-        #[cfg(target_os = "windows")] Numpad3 => enigo::Key::Numpad3,
-        #[cfg(target_os = "macos")] Numpad4 => enigo::Key::Other(86),
-        #[cfg(target_os = "windows")] Numpad4 => enigo::Key::Numpad4,
-        #[cfg(target_os = "macos")] Numpad5 => enigo::Key::Other(87),
-        #[cfg(target_os = "windows")] Numpad5 => enigo::Key::Numpad5,
-        #[cfg(target_os = "macos")] Numpad6 => enigo::Key::Other(88),
-        #[cfg(target_os = "windows")] Numpad6 => enigo::Key::Numpad6,
-        #[cfg(target_os = "macos")] Numpad7 => enigo::Key::Other(89),
-        #[cfg(target_os = "windows")] Numpad7 => enigo::Key::Numpad7,
-        #[cfg(target_os = "macos")] Numpad8 => enigo::Key::Other(91),
-        #[cfg(target_os = "windows")] Numpad8 => enigo::Key::Numpad8,
-        #[cfg(target_os = "macos")] Numpad9 => enigo::Key::Other(92),
-        #[cfg(target_os = "windows")] Numpad9 => enigo::Key::Numpad9,
-        #[cfg(target_os = "macos")] NumpadAdd => enigo::Key::Other(69),
-        #[cfg(target_os = "windows")] NumpadAdd => enigo::Key::Add,
-        #[cfg(target_os = "macos")] NumpadSubtract => enigo::Key::Other(78),
-        #[cfg(target_os = "windows")] NumpadSubtract => enigo::Key::Subtract,
-        #[cfg(target_os = "macos")] NumpadMultiply => enigo::Key::Other(67),
-        #[cfg(target_os = "windows")] NumpadMultiply => enigo::Key::Multiply,
-        #[cfg(target_os = "macos")] NumpadDivide => enigo::Key::Other(75),
-        #[cfg(target_os = "windows")] NumpadDivide => enigo::Key::Divide,
-        #[cfg(target_os = "macos")] NumpadEnter => enigo::Key::Other(76),
-        #[cfg(target_os = "windows")] NumpadEnter => enigo::Key::Return,
-        #[cfg(target_os = "macos")] NumpadDecimal => enigo::Key::Other(65),
-        #[cfg(target_os = "windows")] NumpadDecimal => enigo::Key::Decimal,
-    } => enigo::Key
+define_keys! {
+        A(A) => enigo::Key::Unicode('a'),
+        B(B) => enigo::Key::Unicode('b'),
+        C(C) => enigo::Key::Unicode('c'),
+        D(D) => enigo::Key::Unicode('d'),
+        E(E) => enigo::Key::Unicode('e'),
+        F(F) => enigo::Key::Unicode('f'),
+        G(G) => enigo::Key::Unicode('g'),
+        H(H) => enigo::Key::Unicode('h'),
+        I(I) => enigo::Key::Unicode('i'),
+        J(J) => enigo::Key::Unicode('j'),
+        K(K) => enigo::Key::Unicode('k'),
+        L(L) => enigo::Key::Unicode('l'),
+        M(M) => enigo::Key::Unicode('m'),
+        N(N) => enigo::Key::Unicode('n'),
+        O(O) => enigo::Key::Unicode('o'),
+        P(P) => enigo::Key::Unicode('p'),
+        Q(Q) => enigo::Key::Unicode('q'),
+        R(R) => enigo::Key::Unicode('r'),
+        S(S) => enigo::Key::Unicode('s'),
+        T(T) => enigo::Key::Unicode('t'),
+        U(U) => enigo::Key::Unicode('u'),
+        V(V) => enigo::Key::Unicode('v'),
+        W(W) => enigo::Key::Unicode('w'),
+        X(X) => enigo::Key::Unicode('x'),
+        Y(Y) => enigo::Key::Unicode('y'),
+        Z(Z) => enigo::Key::Unicode('z'),
+
+        Key0(Key0) => enigo::Key::Unicode('0'),
+        Key1(Key1) => enigo::Key::Unicode('1'),
+        Key2(Key2) => enigo::Key::Unicode('2'),
+        Key3(Key3) => enigo::Key::Unicode('3'),
+        Key4(Key4) => enigo::Key::Unicode('4'),
+        Key5(Key5) => enigo::Key::Unicode('5'),
+        Key6(Key6) => enigo::Key::Unicode('6'),
+        Key7(Key7) => enigo::Key::Unicode('7'),
+        Key8(Key8) => enigo::Key::Unicode('8'),
+        Key9(Key9) => enigo::Key::Unicode('9'),
+
+        F1(F1) => enigo::Key::F1,
+        F2(F2) => enigo::Key::F2,
+        F3(F3) => enigo::Key::F3,
+        F4(F4) => enigo::Key::F4,
+        F5(F5) => enigo::Key::F5,
+        F6(F6) => enigo::Key::F6,
+        F7(F7) => enigo::Key::F7,
+        F8(F8) => enigo::Key::F8,
+        F9(F9) => enigo::Key::F9,
+        F10(F10) => enigo::Key::F10,
+        F11(F11) => enigo::Key::F11,
+        F12(F12) => enigo::Key::F12,
+        F13(F13) => enigo::Key::F13,
+        F14(F14) => enigo::Key::F14,
+        F15(F15) => enigo::Key::F15,
+        F16(F16) => enigo::Key::F16,
+        F17(F17) => enigo::Key::F17,
+        F18(F18) => enigo::Key::F18,
+        F19(F19) => enigo::Key::F19,
+        F20(F20) => enigo::Key::F20,
+
+        LShift(LShift) => enigo::Key::LShift,
+        RShift(RShift) => enigo::Key::RShift,
+        LCtrl(LControl) => enigo::Key::LControl,
+        RCtrl(RControl) => enigo::Key::RControl,
+        Tab(Tab) => enigo::Key::Tab,
+        Space(Space) => enigo::Key::Space,
+
+        #[cfg(target_os = "macos")] LCommand(Command) => enigo::Key::Meta,
+        #[cfg(target_os = "windows")] LCommand(LMeta) => enigo::Key::LWin,
+        #[cfg(target_os = "linux")] LCommand(LMeta) => enigo::Key::Meta,
+        #[cfg(target_os = "macos")] RCommand(RCommand) => enigo::Key::Other(0x36),
+        #[cfg(target_os = "windows")] RCommand(RMeta) => enigo::Key::RWin,
+        #[cfg(target_os = "linux")] RCommand(RMeta) => enigo::Key::Other(0xffec), // Super_R
+
+        #[cfg(target_os = "macos")] Alt(LOption) => enigo::Key::Alt,
+        #[cfg(not(target_os = "macos"))] Alt(LAlt) => enigo::Key::Alt,
+        #[cfg(target_os = "macos")] AltGr(ROption) => enigo::Key::Other(0x3d), // 右 Option
+        #[cfg(target_os = "windows")] AltGr(RAlt) => enigo::Key::RMenu,
+        #[cfg(target_os = "linux")] AltGr(RAlt) => enigo::Key::Other(0xfe03), // ISO_Level3_Shift
+        #[cfg(target_os = "macos")] Option(LOption) => enigo::Key::Option,
+        #[cfg(not(target_os = "macos"))] Option(LAlt) => enigo::Key::Alt,
+        #[cfg(target_os = "macos")] Super(Command) => enigo::Key::Meta,
+        #[cfg(target_os = "windows")] Super(LMeta) => enigo::Key::LWin,
+        #[cfg(target_os = "linux")] Super(LMeta) => enigo::Key::Meta,
+        #[cfg(target_os = "macos")] Menu => enigo::Key::Other(0x6e),
+        #[cfg(target_os = "windows")] Menu => enigo::Key::Apps,
+        #[cfg(target_os = "linux")] Menu => enigo::Key::LMenu,
+
+        CapsLock(CapsLock) => enigo::Key::CapsLock,
+        Enter(Enter) => enigo::Key::Return,
+        Esc(Escape) => enigo::Key::Escape,
+        Backspace(Backspace) => enigo::Key::Backspace,
+        Delete(Delete) => enigo::Key::Delete,
+
+        Up(Up) => enigo::Key::UpArrow,
+        Down(Down) => enigo::Key::DownArrow,
+        Left(Left) => enigo::Key::LeftArrow,
+        Right(Right) => enigo::Key::RightArrow,
+
+        Home(Home) => enigo::Key::Home,
+        End(End) => enigo::Key::End,
+        PageUp(PageUp) => enigo::Key::PageUp,
+        PageDown(PageDown) => enigo::Key::PageDown,
+
+        // macOS には Insert/PrintScreen/ScrollLock/Pause/NumLock がないので、同じ位置のキーを使う
+        #[cfg(target_os = "macos")] Insert(Insert) => enigo::Key::Help,
+        #[cfg(not(target_os = "macos"))] Insert(Insert) => enigo::Key::Insert,
+        #[cfg(target_os = "macos")] PrintScreen => enigo::Key::F13,
+        #[cfg(not(target_os = "macos"))] PrintScreen => enigo::Key::PrintScr,
+        #[cfg(target_os = "macos")] ScrollLock => enigo::Key::F14,
+        #[cfg(target_os = "windows")] ScrollLock => enigo::Key::Scroll,
+        #[cfg(target_os = "linux")] ScrollLock => enigo::Key::ScrollLock,
+        #[cfg(target_os = "macos")] Pause => enigo::Key::F15,
+        #[cfg(not(target_os = "macos"))] Pause => enigo::Key::Pause,
+        #[cfg(target_os = "macos")] NumLock => enigo::Key::Other(0x47), // テンキーの Clear
+        #[cfg(not(target_os = "macos"))] NumLock => enigo::Key::Numlock,
+
+        VolumeUp => enigo::Key::VolumeUp,
+        VolumeDown => enigo::Key::VolumeDown,
+        VolumeMute => enigo::Key::VolumeMute,
+        MediaPlayPause => enigo::Key::MediaPlayPause,
+        MediaNextTrack => enigo::Key::MediaNextTrack,
+        MediaPrevTrack => enigo::Key::MediaPrevTrack,
+
+        #[cfg(target_os = "macos")] Numpad0(Numpad0) => enigo::Key::Other(82),
+        #[cfg(target_os = "windows")] Numpad0(Numpad0) => enigo::Key::Numpad0,
+        #[cfg(target_os = "linux")] Numpad0(Numpad0) => enigo::Key::Other(0xffb0),
+        #[cfg(target_os = "macos")] Numpad1(Numpad1) => enigo::Key::Other(83),
+        #[cfg(target_os = "windows")] Numpad1(Numpad1) => enigo::Key::Numpad1,
+        #[cfg(target_os = "linux")] Numpad1(Numpad1) => enigo::Key::Other(0xffb1),
+        #[cfg(target_os = "macos")] Numpad2(Numpad2) => enigo::Key::Other(84),
+        #[cfg(target_os = "windows")] Numpad2(Numpad2) => enigo::Key::Numpad2,
+        #[cfg(target_os = "linux")] Numpad2(Numpad2) => enigo::Key::Other(0xffb2),
+        #[cfg(target_os = "macos")] Numpad3(Numpad3) => enigo::Key::Other(85),
+        #[cfg(target_os = "windows")] Numpad3(Numpad3) => enigo::Key::Numpad3,
+        #[cfg(target_os = "linux")] Numpad3(Numpad3) => enigo::Key::Other(0xffb3),
+        #[cfg(target_os = "macos")] Numpad4(Numpad4) => enigo::Key::Other(86),
+        #[cfg(target_os = "windows")] Numpad4(Numpad4) => enigo::Key::Numpad4,
+        #[cfg(target_os = "linux")] Numpad4(Numpad4) => enigo::Key::Other(0xffb4),
+        #[cfg(target_os = "macos")] Numpad5(Numpad5) => enigo::Key::Other(87),
+        #[cfg(target_os = "windows")] Numpad5(Numpad5) => enigo::Key::Numpad5,
+        #[cfg(target_os = "linux")] Numpad5(Numpad5) => enigo::Key::Other(0xffb5),
+        #[cfg(target_os = "macos")] Numpad6(Numpad6) => enigo::Key::Other(88),
+        #[cfg(target_os = "windows")] Numpad6(Numpad6) => enigo::Key::Numpad6,
+        #[cfg(target_os = "linux")] Numpad6(Numpad6) => enigo::Key::Other(0xffb6),
+        #[cfg(target_os = "macos")] Numpad7(Numpad7) => enigo::Key::Other(89),
+        #[cfg(target_os = "windows")] Numpad7(Numpad7) => enigo::Key::Numpad7,
+        #[cfg(target_os = "linux")] Numpad7(Numpad7) => enigo::Key::Other(0xffb7),
+        #[cfg(target_os = "macos")] Numpad8(Numpad8) => enigo::Key::Other(91),
+        #[cfg(target_os = "windows")] Numpad8(Numpad8) => enigo::Key::Numpad8,
+        #[cfg(target_os = "linux")] Numpad8(Numpad8) => enigo::Key::Other(0xffb8),
+        #[cfg(target_os = "macos")] Numpad9(Numpad9) => enigo::Key::Other(92),
+        #[cfg(target_os = "windows")] Numpad9(Numpad9) => enigo::Key::Numpad9,
+        #[cfg(target_os = "linux")] Numpad9(Numpad9) => enigo::Key::Other(0xffb9),
+        #[cfg(target_os = "macos")] NumpadAdd(NumpadAdd) => enigo::Key::Other(69),
+        #[cfg(target_os = "windows")] NumpadAdd(NumpadAdd) => enigo::Key::Add,
+        #[cfg(target_os = "linux")] NumpadAdd(NumpadAdd) => enigo::Key::Other(0xffab),
+        #[cfg(target_os = "macos")] NumpadSubtract(NumpadSubtract) => enigo::Key::Other(78),
+        #[cfg(target_os = "windows")] NumpadSubtract(NumpadSubtract) => enigo::Key::Subtract,
+        #[cfg(target_os = "linux")] NumpadSubtract(NumpadSubtract) => enigo::Key::Other(0xffad),
+        #[cfg(target_os = "macos")] NumpadMultiply(NumpadMultiply) => enigo::Key::Other(67),
+        #[cfg(target_os = "windows")] NumpadMultiply(NumpadMultiply) => enigo::Key::Multiply,
+        #[cfg(target_os = "linux")] NumpadMultiply(NumpadMultiply) => enigo::Key::Other(0xffaa),
+        #[cfg(target_os = "macos")] NumpadDivide(NumpadDivide) => enigo::Key::Other(75),
+        #[cfg(target_os = "windows")] NumpadDivide(NumpadDivide) => enigo::Key::Divide,
+        #[cfg(target_os = "linux")] NumpadDivide(NumpadDivide) => enigo::Key::Other(0xffaf),
+        #[cfg(target_os = "macos")] NumpadEnter(NumpadEnter) => enigo::Key::Other(76),
+        #[cfg(target_os = "windows")] NumpadEnter(NumpadEnter) => enigo::Key::Return,
+        #[cfg(target_os = "linux")] NumpadEnter(NumpadEnter) => enigo::Key::Other(0xff8d),
+        #[cfg(target_os = "macos")] NumpadDecimal(NumpadDecimal) => enigo::Key::Other(65),
+        #[cfg(target_os = "windows")] NumpadDecimal(NumpadDecimal) => enigo::Key::Decimal,
+        #[cfg(target_os = "linux")] NumpadDecimal(NumpadDecimal) => enigo::Key::Other(0xffae),
+        #[cfg(target_os = "macos")] NumpadEquals(NumpadEquals) => enigo::Key::Other(81),
+        #[cfg(target_os = "windows")] NumpadEquals(NumpadEquals) => enigo::Key::Other(0x92),
+        #[cfg(target_os = "linux")] NumpadEquals(NumpadEquals) => enigo::Key::Other(0xffbd),
+
+        Grave(Grave) => enigo::Key::Unicode('`'),
+        Minus(Minus) => enigo::Key::Unicode('-'),
+        Equal(Equal) => enigo::Key::Unicode('='),
+        LeftBracket(LeftBracket) => enigo::Key::Unicode('['),
+        RightBracket(RightBracket) => enigo::Key::Unicode(']'),
+        BackSlash(BackSlash) => enigo::Key::Unicode('\\'),
+        Semicolon(Semicolon) => enigo::Key::Unicode(';'),
+        Apostrophe(Apostrophe) => enigo::Key::Unicode('\''),
+        Comma(Comma) => enigo::Key::Unicode(','),
+        Dot(Dot) => enigo::Key::Unicode('.'),
+        Slash(Slash) => enigo::Key::Unicode('/'),
+}
+
+const ALIASES: &[(&str, Key)] = &[
+    ("ctrl", Key::LCtrl),
+    ("control", Key::LCtrl),
+    ("shift", Key::LShift),
+    ("cmd", Key::LCommand),
+    ("command", Key::LCommand),
+    ("win", Key::Super),
+    ("meta", Key::Super),
+    ("escape", Key::Esc),
+    ("return", Key::Enter),
+    ("del", Key::Delete),
+    ("ins", Key::Insert),
+];
+
+// 以前の`Key.Query`で使っていた device_query の名前。OS によらず同じものを受け付ける
+pub const QUERY_ALIASES: &[(&str, Key)] = &[
+    ("LControl", Key::LCtrl),
+    ("RControl", Key::RCtrl),
+    ("LMeta", Key::LCommand),
+    ("RMeta", Key::RCommand),
+    ("Command", Key::LCommand),
+    ("LAlt", Key::Alt),
+    ("RAlt", Key::AltGr),
+    ("LOption", Key::Alt),
+    ("ROption", Key::AltGr),
+    ("Escape", Key::Esc),
+];
+
+impl Key {
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+    pub fn from_keycode(keycode: Keycode) -> Option<Key> {
+        Key::iter().find(|key| key.keycode() == Some(keycode))
+    }
+}
+impl FromStr for Key {
+    type Err = strum::ParseError;
+
+    // 大文字小文字は区別しない
+    // device_queryの名前 (LControl, Escape, ...) も受け付ける
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Key::iter()
+            .find(|key| key.name().eq_ignore_ascii_case(s))
+            .or_else(|| {
+                ALIASES
+                    .iter()
+                    .chain(QUERY_ALIASES)
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(s))
+                    .map(|(_, key)| *key)
+            })
+            .ok_or(strum::ParseError::VariantNotFound)
+    }
 }
 
 define_enum_with_into! {
@@ -125,15 +322,241 @@ define_enum_with_into! {
     } => enigo::Coordinate
 }
 
-define_enum_with_into! {
-    #[derive(Debug)]
-    pub enum ButtonSend {
-        Left => enigo::Button::Left,
-        Right => enigo::Button::Right,
-        Middle => enigo::Button::Middle,
-        ScrollUp => enigo::Button::ScrollUp,
-        ScrollDown => enigo::Button::ScrollDown,
-        ScrollLeft => enigo::Button::ScrollLeft,
-        ScrollRight => enigo::Button::ScrollRight,
-    } => enigo::Button
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, strum::VariantNames, strum::IntoStaticStr, strum::EnumIter,
+)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    // 互換性のために残している。mouse.scroll を使う
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+impl Button {
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+    pub fn send(self) -> Option<enigo::Button> {
+        Some(match self {
+            Button::Left => enigo::Button::Left,
+            Button::Right => enigo::Button::Right,
+            Button::Middle => enigo::Button::Middle,
+            #[cfg(not(target_os = "macos"))]
+            Button::Back => enigo::Button::Back,
+            #[cfg(not(target_os = "macos"))]
+            Button::Forward => enigo::Button::Forward,
+            #[cfg(target_os = "macos")]
+            Button::Back | Button::Forward => return None,
+            Button::ScrollUp => enigo::Button::ScrollUp,
+            Button::ScrollDown => enigo::Button::ScrollDown,
+            Button::ScrollLeft => enigo::Button::ScrollLeft,
+            Button::ScrollRight => enigo::Button::ScrollRight,
+        })
+    }
+    // device_queryの`button_pressed`の添字
+    #[cfg(target_os = "windows")]
+    pub fn index(self) -> Option<usize> {
+        match self {
+            Button::Left => Some(1),
+            Button::Right => Some(2),
+            Button::Middle => Some(3),
+            Button::Back => Some(4),
+            Button::Forward => Some(5),
+            _ => None,
+        }
+    }
+    #[cfg(target_os = "linux")]
+    pub fn index(self) -> Option<usize> {
+        match self {
+            Button::Left => Some(1),
+            Button::Middle => Some(2),
+            Button::Right => Some(3),
+            _ => None,
+        }
+    }
+    #[cfg(target_os = "macos")]
+    pub fn index(self) -> Option<usize> {
+        match self {
+            Button::Left => Some(1),
+            Button::Right => Some(2),
+            Button::Middle => Some(3),
+            _ => None,
+        }
+    }
+    pub fn from_index(index: usize) -> Option<Button> {
+        Button::iter().find(|button| button.index() == Some(index))
+    }
+
+    // スクリプトで番号を指定したときのボタン。OS によらず同じ番号を使う
+    pub fn number(self) -> Option<usize> {
+        NUMBERED
+            .iter()
+            .position(|button| *button == self)
+            .map(|i| i + 1)
+    }
+    pub fn from_number(number: usize) -> Option<Button> {
+        NUMBERED.get(number.checked_sub(1)?).copied()
+    }
+}
+
+const NUMBERED: [Button; 5] = [
+    Button::Left,
+    Button::Right,
+    Button::Middle,
+    Button::Back,
+    Button::Forward,
+];
+impl FromStr for Button {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Button::iter()
+            .find(|button| button.name().eq_ignore_ascii_case(s))
+            .or_else(|| match s.to_ascii_uppercase().as_str() {
+                "X1" => Some(Button::Back),
+                "X2" => Some(Button::Forward),
+                _ => None,
+            })
+            .ok_or(strum::ParseError::VariantNotFound)
+    }
+}
+
+#[derive(Clone, Copy, strum::EnumString, strum::VariantNames)]
+pub enum Curve {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Bezier,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use device_query::Keycode;
+    use strum::IntoEnumIterator;
+
+    use super::{Button, Key};
+
+    const ENUM_DOCS: &str = include_str!("../../data/.vscode/yam-docs/meta/enum.lua");
+
+    fn documented(name: &str) -> Vec<&'static str> {
+        let start = ENUM_DOCS
+            .find(&format!("{} = {{", name))
+            .expect("enum not documented");
+        ENUM_DOCS[start..]
+            .lines()
+            .skip(1)
+            .take_while(|line| line.trim() != "}")
+            .filter_map(|line| line.split_once('=').map(|(key, _)| key.trim()))
+            .collect()
+    }
+
+    #[test]
+    fn documented_send_keys_parse() {
+        let names = documented("Key.Send");
+        assert!(!names.is_empty());
+        for name in names {
+            assert!(Key::from_str(name).is_ok(), "{} does not parse", name);
+        }
+    }
+
+    #[test]
+    fn documented_query_keys_parse() {
+        let names = documented("Key.Query");
+        assert!(!names.is_empty());
+        for name in names {
+            let key = Key::from_str(name).unwrap_or_else(|_| panic!("{} does not parse", name));
+            assert!(key.keycode().is_some(), "{} cannot be queried", name);
+        }
+        assert_eq!(Key::from_str("LMeta"), Ok(Key::LCommand));
+        assert_eq!(Key::from_str("ROption"), Ok(Key::AltGr));
+    }
+
+    #[test]
+    fn aliases_are_case_insensitive() {
+        for name in ["LCtrl", "lctrl", "ctrl", "Control", "LControl"] {
+            assert_eq!(Key::from_str(name), Ok(Key::LCtrl), "{}", name);
+        }
+        assert_eq!(Key::from_str("escape"), Ok(Key::Esc));
+        assert_eq!(Key::from_str("a"), Ok(Key::A));
+        assert!(Key::from_str("NotAKey").is_err());
+    }
+
+    #[test]
+    fn keycode_mapping_roundtrips() {
+        for key in Key::iter() {
+            if let Some(keycode) = key.keycode() {
+                let back = Key::from_keycode(keycode).unwrap();
+                assert_eq!(back.keycode(), Some(keycode), "{}", key.name());
+            }
+        }
+        assert_eq!(Key::from_keycode(Keycode::LControl), Some(Key::LCtrl));
+        assert_eq!(Key::from_keycode(Keycode::A), Some(Key::A));
+    }
+
+    #[test]
+    fn button_numbers_do_not_depend_on_os() {
+        assert_eq!(Button::from_number(1), Some(Button::Left));
+        assert_eq!(Button::from_number(2), Some(Button::Right));
+        assert_eq!(Button::from_number(3), Some(Button::Middle));
+        assert_eq!(Button::from_number(4), Some(Button::Back));
+        assert_eq!(Button::from_number(5), Some(Button::Forward));
+        assert_eq!(Button::from_number(0), None);
+        assert_eq!(Button::from_number(6), None);
+        for button in Button::iter() {
+            if let Some(number) = button.number() {
+                assert_eq!(Button::from_number(number), Some(button));
+            }
+        }
+        assert_eq!(Button::ScrollUp.number(), None);
+    }
+
+    fn key(name: &str) -> enigo::Key {
+        Key::from_str(name).unwrap().into()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_mapping() {
+        assert_eq!(key("LCommand"), enigo::Key::Meta);
+        assert_eq!(key("Option"), enigo::Key::Alt);
+        assert_eq!(key("NumLock"), enigo::Key::Numlock);
+        assert_eq!(key("Numpad0"), enigo::Key::Other(0xffb0));
+        assert_eq!(key("NumpadEnter"), enigo::Key::Other(0xff8d));
+
+        assert_eq!(Button::from_index(2), Some(Button::Middle));
+        assert_eq!(Button::from_str("x1"), Ok(Button::Back));
+        assert_eq!(Button::Back.index(), None);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn windows_mapping() {
+        assert_eq!(key("LCommand"), enigo::Key::LWin);
+        assert_eq!(key("AltGr"), enigo::Key::RMenu);
+        assert_eq!(key("ScrollLock"), enigo::Key::Scroll);
+        assert_eq!(key("Numpad0"), enigo::Key::Numpad0);
+
+        assert_eq!(Button::from_index(4), Some(Button::Back));
+        assert_eq!(Button::Forward.send(), Some(enigo::Button::Forward));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn macos_mapping() {
+        assert_eq!(key("LCommand"), enigo::Key::Meta);
+        assert_eq!(key("Option"), enigo::Key::Option);
+        assert_eq!(key("Insert"), enigo::Key::Help);
+        assert_eq!(key("Numpad0"), enigo::Key::Other(82));
+
+        assert_eq!(Button::from_index(3), Some(Button::Middle));
+        assert_eq!(Button::Back.send(), None);
+    }
 }
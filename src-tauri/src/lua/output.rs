@@ -0,0 +1,302 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use enigo::{
+    Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, NewConError, Settings,
+};
+use strum::IntoEnumIterator;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{
+    edge::Input,
+    input, model,
+    pause::PauseState,
+    throttle::{Admission, InputKind, Limiter, RateLimits},
+    timeline::wait_until,
+};
+
+// 待っている間に一時停止したかを確認する間隔
+const PAUSE_CHECK: Duration = Duration::from_millis(10);
+
+// スクリプトが送る入力はすべてここを通し、送った内容を入力の監視に知らせる
+pub struct Output {
+    enigo: Enigo,
+    held: HeldInputs,
+    // 一時停止で待っていた時間の合計
+    paused: Duration,
+    script: Option<ScriptControl>,
+}
+
+// スクリプトから送るときの制限と一時停止
+struct ScriptControl {
+    limiter: Limiter,
+    exit_flag: Arc<AtomicBool>,
+    pause: Arc<PauseState>,
+    // 送りきれないほど溜まったら、呼び出し側が止めるまで以降の入力を捨てる
+    flooded: bool,
+}
+
+// 一時停止していた時間を除いて経過時間を測る
+pub struct Timer {
+    start: Instant,
+    paused: Duration,
+}
+
+impl Output {
+    pub fn new() -> Result<Self, NewConError> {
+        Ok(Output {
+            enigo: Enigo::new(&Settings::default())?,
+            held: HeldInputs::default(),
+            paused: Duration::ZERO,
+            script: None,
+        })
+    }
+    pub fn with_script(
+        mut self,
+        limits: &RateLimits,
+        exit_flag: Arc<AtomicBool>,
+        pause: Arc<PauseState>,
+    ) -> Self {
+        self.script = Some(ScriptControl {
+            limiter: Limiter::new(limits, Instant::now()),
+            exit_flag,
+            pause,
+            flooded: false,
+        });
+        self
+    }
+
+    // 制限され続けて止めたか。一度だけ true を返す
+    pub fn take_flood(&mut self) -> bool {
+        self.script
+            .as_mut()
+            .is_some_and(|script| std::mem::take(&mut script.flooded))
+    }
+
+    pub fn timer(&self) -> Timer {
+        Timer {
+            start: Instant::now(),
+            paused: self.paused,
+        }
+    }
+    // timer から at 経つまで待つ。一時停止している間は押しているキーを離して待つ
+    // 停止されたら false
+    pub fn wait(&mut self, timer: &Timer, at: Duration, stop_flag: &AtomicBool) -> bool {
+        loop {
+            if !self.wait_resumed(stop_flag) {
+                return false;
+            }
+            let deadline = timer.start + at + (self.paused - timer.paused);
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            if !wait_until(now + (deadline - now).min(PAUSE_CHECK), stop_flag) {
+                return false;
+            }
+        }
+    }
+
+    // 一時停止していれば、押しているキーを離して再開を待ち、押し直す。停止されたら false
+    pub fn wait_resumed(&mut self, stop_flag: &AtomicBool) -> bool {
+        if stop_flag.load(Ordering::SeqCst) {
+            return false;
+        }
+        let Some(pause) = self.script.as_ref().map(|script| Arc::clone(&script.pause)) else {
+            return true;
+        };
+        if !pause.is_paused() {
+            return true;
+        }
+
+        let since = Instant::now();
+        self.sync_held(true);
+        let resumed = pause.wait(stop_flag);
+        self.paused += since.elapsed();
+        if resumed {
+            self.sync_held(false);
+        }
+        resumed
+    }
+    // 何も送っていない間に一時停止・再開されたときに呼ぶ
+    pub fn sync_pause(&mut self) {
+        if let Some(paused) = self.script.as_ref().map(|script| script.pause.is_paused()) {
+            self.sync_held(paused);
+        }
+    }
+    // 停止したときに呼ぶ。再開しても押し直さない
+    pub fn release_held(&mut self) {
+        self.sync_held(true);
+        self.held = HeldInputs::default();
+    }
+
+    fn sync_held(&mut self, paused: bool) {
+        if paused == self.held.released {
+            return;
+        }
+        let direction = if paused {
+            Direction::Release
+        } else {
+            Direction::Press
+        };
+        for key in self.held.keys.clone() {
+            if let Err(err) = self.send_key(key, direction) {
+                eprintln!("Failed to update held key {:?}: {}", key, err);
+            }
+        }
+        for button in self.held.buttons.clone() {
+            if let Err(err) = self.send_button(button, direction) {
+                eprintln!("Failed to update held button {:?}: {}", button, err);
+            }
+        }
+        self.held.released = paused;
+    }
+
+    // 送ってよければ true、待っている間に停止されたら false。暴走とみなしたらエラーで抜けさせる
+    fn admit(&mut self, kind: InputKind, cost: usize) -> anyhow::Result<bool> {
+        let Some(script) = &self.script else {
+            return Ok(true);
+        };
+        if script.flooded {
+            anyhow::bail!("too many inputs");
+        }
+        let exit_flag = Arc::clone(&script.exit_flag);
+        if !self.wait_resumed(&exit_flag) {
+            return Ok(false);
+        }
+
+        let Some(script) = &mut self.script else {
+            return Ok(true);
+        };
+        match script.limiter.admit(kind, cost as u32, Instant::now()) {
+            Admission::Now => Ok(true),
+            Admission::Wait(wait) => Ok(wait_until(Instant::now() + wait, &exit_flag)),
+            Admission::Flood => {
+                script.flooded = true;
+                anyhow::bail!("too many inputs");
+            }
+        }
+    }
+
+    // 押したままにならないよう、離す入力は制限しない
+    pub fn key(&mut self, key: Key, direction: Direction) -> anyhow::Result<()> {
+        if direction != Direction::Release && !self.admit(InputKind::Keyboard, 1)? {
+            return Ok(());
+        }
+        self.send_key(key, direction)?;
+        update(&mut self.held.keys, key, direction);
+        Ok(())
+    }
+    pub fn text(&mut self, text: &str) -> anyhow::Result<()> {
+        if !self.admit(InputKind::Keyboard, text.graphemes(true).count())? {
+            return Ok(());
+        }
+        for char in text.chars() {
+            report_key(char_key(char));
+        }
+        self.enigo.text(text)?;
+        Ok(())
+    }
+    pub fn button(&mut self, button: Button, direction: Direction) -> anyhow::Result<()> {
+        if direction != Direction::Release && !self.admit(InputKind::MouseButton, 1)? {
+            return Ok(());
+        }
+        self.send_button(button, direction)?;
+        update(&mut self.held.buttons, button, direction);
+        Ok(())
+    }
+    pub fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> anyhow::Result<()> {
+        if !self.admit(InputKind::MouseMove, 1)? {
+            return Ok(());
+        }
+        let position = match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => {
+                let (from_x, from_y) = self.enigo.location()?;
+                (from_x + x, from_y + y)
+            }
+        };
+        input::injected_position(position);
+        self.enigo.move_mouse(x, y, coordinate)?;
+        Ok(())
+    }
+    pub fn scroll(&mut self, length: i32, axis: Axis) -> anyhow::Result<()> {
+        if !self.admit(InputKind::MouseMove, 1)? {
+            return Ok(());
+        }
+        self.enigo.scroll(length, axis)?;
+        Ok(())
+    }
+    pub fn location(&self) -> anyhow::Result<(i32, i32)> {
+        Ok(self.enigo.location()?)
+    }
+
+    fn send_key(&mut self, key: Key, direction: Direction) -> anyhow::Result<()> {
+        report_key(key);
+        self.enigo.key(key, direction)?;
+        Ok(())
+    }
+    fn send_button(&mut self, button: Button, direction: Direction) -> anyhow::Result<()> {
+        if let Some(index) = model::Button::iter()
+            .find(|model| model.send() == Some(button))
+            .and_then(model::Button::index)
+        {
+            input::injected_input(Input::Button(index));
+        }
+        self.enigo.button(button, direction)?;
+        Ok(())
+    }
+}
+
+// 一時停止中に押しっぱなしにならないよう、押しているキーとボタンを覚えておく
+#[derive(Default)]
+struct HeldInputs {
+    keys: Vec<Key>,
+    buttons: Vec<Button>,
+    released: bool,
+}
+
+fn update<T: PartialEq>(held: &mut Vec<T>, input: T, direction: Direction) {
+    match direction {
+        Direction::Press if !held.contains(&input) => held.push(input),
+        Direction::Release => held.retain(|held| *held != input),
+        _ => {}
+    }
+}
+
+fn char_key(char: char) -> Key {
+    match char {
+        '\n' | '\r' => Key::Return,
+        '\t' => Key::Tab,
+        ' ' => Key::Space,
+        char => Key::Unicode(char),
+    }
+}
+
+// 送ったキーで押されるはずのキー。大文字や記号は Shift と一緒に押されることがある
+fn report_key(key: Key) {
+    let (key, shifted) = match key {
+        Key::Unicode(char) if char.is_uppercase() => (
+            Key::Unicode(char.to_lowercase().next().unwrap_or(char)),
+            true,
+        ),
+        key => (key, false),
+    };
+    let mut found = false;
+    for keycode in model::Key::iter()
+        .filter(|model| Key::from(*model) == key)
+        .filter_map(model::Key::keycode)
+    {
+        input::injected_input(Input::Key(keycode));
+        found = true;
+    }
+    if shifted || (!found && matches!(key, Key::Unicode(_))) {
+        input::injected_input(Input::Key(device_query::Keycode::LShift));
+        input::injected_input(Input::Key(device_query::Keycode::RShift));
+    }
+}
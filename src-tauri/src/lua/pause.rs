@@ -0,0 +1,142 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+// 停止されたかを確認する間隔
+const CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Default)]
+struct Paused {
+    // 一時停止した時刻。再開すると None に戻る
+    since: Option<Instant>,
+    // これまでに一時停止していた時間の合計
+    total: Duration,
+}
+
+#[derive(Default)]
+pub struct PauseState {
+    paused: Mutex<Paused>,
+    changed: Condvar,
+}
+impl PauseState {
+    // すでに一時停止している場合は false
+    pub fn pause(&self) -> bool {
+        let mut paused = self.paused.lock().unwrap();
+        if paused.since.is_some() {
+            return false;
+        }
+        paused.since = Some(Instant::now());
+        self.changed.notify_all();
+        true
+    }
+    // 一時停止していた時間を返す
+    pub fn resume(&self) -> Option<Duration> {
+        let mut paused = self.paused.lock().unwrap();
+        let elapsed = paused.since.take()?.elapsed();
+        paused.total += elapsed;
+        self.changed.notify_all();
+        Some(elapsed)
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused.lock().unwrap().since.is_some()
+    }
+    // 現在の一時停止も含めた合計
+    pub fn total(&self) -> Duration {
+        self.total_at(Instant::now())
+    }
+    pub fn total_at(&self, now: Instant) -> Duration {
+        let paused = self.paused.lock().unwrap();
+        paused.total
+            + paused
+                .since
+                .map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+    }
+
+    // 再開されるまで待つ。stop_flag が立ったら false
+    pub fn wait(&self, stop_flag: &AtomicBool) -> bool {
+        let mut paused = self.paused.lock().unwrap();
+        while paused.since.is_some() {
+            if stop_flag.load(Ordering::SeqCst) {
+                return false;
+            }
+            paused = self.changed.wait_timeout(paused, CHECK_INTERVAL).unwrap().0;
+        }
+        !stop_flag.load(Ordering::SeqCst)
+    }
+
+    // 一時停止している間は残り時間を減らさずに待つ。stop_flag が立ったら false
+    pub fn sleep(&self, duration: Duration, stop_flag: &AtomicBool) -> bool {
+        let mut remaining = duration;
+        loop {
+            if !self.wait(stop_flag) {
+                return false;
+            }
+
+            let start = Instant::now();
+            let deadline = start + remaining;
+            let mut paused = self.paused.lock().unwrap();
+            while paused.since.is_none() {
+                if stop_flag.load(Ordering::SeqCst) {
+                    return false;
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    return true;
+                }
+                paused = self
+                    .changed
+                    .wait_timeout(paused, (deadline - now).min(CHECK_INTERVAL))
+                    .unwrap()
+                    .0;
+            }
+            remaining = remaining.saturating_sub(start.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    #[test]
+    fn sleep_does_not_count_paused_time() {
+        let state = Arc::new(PauseState::default());
+        let stop_flag = AtomicBool::new(false);
+
+        let pauser = {
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                state.pause();
+                thread::sleep(Duration::from_millis(200));
+                state.resume()
+            })
+        };
+        let start = Instant::now();
+        assert!(state.sleep(Duration::from_millis(100), &stop_flag));
+        let elapsed = start.elapsed();
+
+        let paused = pauser.join().unwrap().unwrap();
+        assert!(paused >= Duration::from_millis(200));
+        assert_eq!(state.total(), paused);
+        assert!(elapsed >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn stop_flag_ends_pause() {
+        let state = PauseState::default();
+        let stop_flag = AtomicBool::new(true);
+        assert!(state.pause());
+        assert!(!state.pause());
+        assert!(!state.wait(&stop_flag));
+        assert!(!state.sleep(Duration::from_secs(10), &stop_flag));
+        assert!(state.resume().is_some());
+        assert!(state.resume().is_none());
+    }
+}
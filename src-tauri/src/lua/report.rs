@@ -0,0 +1,167 @@
+use std::{fmt::Write, fs, path::PathBuf, time::Duration};
+
+use super::ScriptError;
+
+// エラー位置の前後に表示する行数
+const CONTEXT_LINES: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub number: u32,
+    pub text: String,
+    pub highlighted: bool,
+}
+
+// エラーウィンドウと履歴に表示する内容
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    pub title: String,
+    pub path: PathBuf,
+    pub summary: &'static str,
+    pub message: String,
+    // エラーが起きたファイルと行。スクリプト自身とは限らない
+    pub file: Option<PathBuf>,
+    pub line: Option<u32>,
+    pub source: Vec<SourceLine>,
+    pub traceback: Option<String>,
+    pub elapsed: Option<Duration>,
+}
+impl ErrorReport {
+    pub fn new(
+        title: impl Into<String>,
+        path: impl Into<PathBuf>,
+        err: &ScriptError,
+        elapsed: Option<Duration>,
+    ) -> Self {
+        let path = path.into();
+        let (file, line) = match err.location() {
+            Some(location) => (
+                Some(PathBuf::from(&location.source)).filter(|file| file.is_file()),
+                Some(location.line),
+            ),
+            None => (None, None),
+        };
+        let source = match (&file, line) {
+            (Some(file), Some(line)) => fs::read_to_string(file)
+                .map(|text| source_context(&text, line))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        ErrorReport {
+            title: title.into(),
+            path,
+            summary: summary(err),
+            message: err.to_string(),
+            file,
+            line,
+            source,
+            traceback: err.traceback().map(str::to_string),
+            elapsed,
+        }
+    }
+
+    pub fn elapsed_text(&self) -> Option<String> {
+        self.elapsed.map(format_elapsed)
+    }
+
+    // クリップボードにコピーする文面
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{}", self.summary);
+        let _ = writeln!(text);
+        let _ = writeln!(text, "スクリプト: {}", self.title);
+        let _ = writeln!(text, "ファイル: {}", self.path.display());
+        if let Some(elapsed) = self.elapsed_text() {
+            let _ = writeln!(text, "実行時間: {}", elapsed);
+        }
+        let _ = writeln!(text, "エラー: {}", self.message);
+        if !self.source.is_empty() {
+            let _ = writeln!(text);
+            let width = self
+                .source
+                .last()
+                .map_or(0, |line| line.number.to_string().len());
+            for line in &self.source {
+                let _ = writeln!(
+                    text,
+                    "{} {:>width$} | {}",
+                    if line.highlighted { ">" } else { " " },
+                    line.number,
+                    line.text,
+                );
+            }
+        }
+        if let Some(traceback) = &self.traceback {
+            let _ = writeln!(text);
+            let _ = writeln!(text, "stack traceback:");
+            let _ = writeln!(
+                text,
+                "{}",
+                traceback.trim_start_matches("stack traceback:\n")
+            );
+        }
+        text
+    }
+}
+
+fn summary(err: &ScriptError) -> &'static str {
+    match err {
+        ScriptError::Load { .. } => "Luaスクリプトを読み込めませんでした。",
+        ScriptError::Permission { .. } => "Luaスクリプトに必要な権限が許可されていません。",
+        ScriptError::InputBackend { .. } => "キーボードやマウスの操作に失敗しました。",
+        ScriptError::Limit { .. } => "Luaスクリプトが制限を超えたため停止しました。",
+        ScriptError::Interrupted | ScriptError::Runtime { .. } => {
+            "Luaスクリプトの実行中にエラーが発生しました。"
+        }
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 60 {
+        format!("{}分{}秒", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}秒", elapsed.as_secs_f64())
+    }
+}
+
+fn source_context(text: &str, line: u32) -> Vec<SourceLine> {
+    let first = line.saturating_sub(CONTEXT_LINES).max(1);
+    text.lines()
+        .zip(1..)
+        .skip(first as usize - 1)
+        .take_while(|(_, number)| *number <= line + CONTEXT_LINES)
+        .map(|(text, number)| SourceLine {
+            number,
+            text: text.to_string(),
+            highlighted: number == line,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_context_around_line() {
+        let text = (1..=10)
+            .map(|n| format!("line{}", n))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let lines = source_context(&text, 2);
+        assert_eq!(
+            lines.iter().map(|line| line.number).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert!(lines[1].highlighted && lines[1].text == "line2");
+
+        let lines = source_context(&text, 9);
+        assert_eq!(
+            lines.iter().map(|line| line.number).collect::<Vec<_>>(),
+            [6, 7, 8, 9, 10]
+        );
+    }
+}
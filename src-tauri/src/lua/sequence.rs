@@ -0,0 +1,248 @@
+use std::str::FromStr;
+
+use enigo::{Direction, Key};
+
+use super::{model, output::Output};
+
+pub type KeySequence = Vec<(Key, Direction)>;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{message} at position {position}")]
+pub struct SequenceError {
+    pub position: usize,
+    pub message: String,
+}
+
+fn error<T>(position: usize, message: impl Into<String>) -> Result<T, SequenceError> {
+    Err(SequenceError {
+        position: position + 1,
+        message: message.into(),
+    })
+}
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    if let Ok(key) = model::Key::from_str(name) {
+        return Some(key.into());
+    }
+
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Some(Key::Unicode(char)),
+        _ => None,
+    }
+}
+
+pub fn parse_combo(spec: &str) -> Result<KeySequence, SequenceError> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut keys = Vec::new();
+
+    let mut i = 0;
+    loop {
+        let start = i;
+        if chars.get(i) == Some(&'+') {
+            i += 1;
+        } else {
+            while i < chars.len() && chars[i] != '+' {
+                i += 1;
+            }
+        }
+        if start == i {
+            return error(start, "Expected key name");
+        }
+
+        let name: String = chars[start..i].iter().collect();
+        let Some(key) = key_from_name(name.trim()) else {
+            return error(start, format!("Invalid key: {}", name));
+        };
+        keys.push(key);
+
+        if i == chars.len() {
+            break;
+        }
+        // chars[i] == '+'
+        i += 1;
+        if i == chars.len() {
+            return error(i, "Expected key name after '+'");
+        }
+    }
+
+    let mut sequence: KeySequence = keys.iter().map(|key| (*key, Direction::Press)).collect();
+    sequence.extend(keys.iter().rev().map(|key| (*key, Direction::Release)));
+    Ok(sequence)
+}
+
+pub fn parse_send(spec: &str) -> Result<KeySequence, SequenceError> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut sequence = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '{' => {
+                let start = i;
+                // `{}}` は `}` キー
+                let body_start = i + 1;
+                let mut end = body_start + usize::from(chars.get(body_start) == Some(&'}'));
+                while end < chars.len() && chars[end] != '}' {
+                    end += 1;
+                }
+                if end >= chars.len() {
+                    return error(start, "Unclosed '{'");
+                }
+
+                let body: String = chars[body_start..end].iter().collect();
+                let (name, modifier) = match body.rsplit_once(' ') {
+                    Some((name, modifier)) if !name.is_empty() => (name, Some(modifier)),
+                    _ => (body.as_str(), None),
+                };
+                if name.is_empty() {
+                    return error(start, "Expected key name");
+                }
+                let Some(key) = key_from_name(name) else {
+                    return error(body_start, format!("Invalid key: {}", name));
+                };
+
+                let modifier_position = body_start + name.chars().count() + 1;
+                match modifier.map(|m| m.to_ascii_lowercase()).as_deref() {
+                    None => sequence.push((key, Direction::Click)),
+                    Some("down") => sequence.push((key, Direction::Press)),
+                    Some("up") => sequence.push((key, Direction::Release)),
+                    Some(count) => {
+                        let Ok(count) = count.parse::<u32>() else {
+                            return error(
+                                modifier_position,
+                                format!("Expected 'down', 'up' or a count, found '{}'", count),
+                            );
+                        };
+                        sequence.extend((0..count).map(|_| (key, Direction::Click)));
+                    }
+                }
+                i = end + 1;
+            }
+            '}' => return error(i, "Unexpected '}'"),
+            '\n' => {
+                sequence.push((Key::Return, Direction::Click));
+                i += 1;
+            }
+            '\r' => {
+                sequence.push((Key::Return, Direction::Click));
+                i += if chars.get(i + 1) == Some(&'\n') {
+                    2
+                } else {
+                    1
+                };
+            }
+            '\t' => {
+                sequence.push((Key::Tab, Direction::Click));
+                i += 1;
+            }
+            char => {
+                sequence.push((Key::Unicode(char), Direction::Click));
+                i += 1;
+            }
+        }
+    }
+
+    Ok(sequence)
+}
+
+pub fn run(output: &mut Output, sequence: &KeySequence) -> anyhow::Result<()> {
+    let mut held = Vec::new();
+    for (key, direction) in sequence {
+        if let Err(e) = output.key(*key, *direction) {
+            // 修飾キーを押したままにしない
+            for key in held.into_iter().rev() {
+                let _ = output.key(key, Direction::Release);
+            }
+            return Err(e);
+        }
+
+        match direction {
+            Direction::Press => held.push(*key),
+            Direction::Release => held.retain(|held| held != key),
+            Direction::Click => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(result: Result<KeySequence, SequenceError>) -> usize {
+        result.unwrap_err().position
+    }
+
+    #[test]
+    fn parses_combos() {
+        assert_eq!(
+            parse_combo("ctrl+shift+a").unwrap(),
+            [
+                (Key::LControl, Direction::Press),
+                (Key::LShift, Direction::Press),
+                (Key::Unicode('a'), Direction::Press),
+                (Key::Unicode('a'), Direction::Release),
+                (Key::LShift, Direction::Release),
+                (Key::LControl, Direction::Release),
+            ]
+        );
+        // `+` キー自体も指定できる
+        assert_eq!(
+            parse_combo("ctrl++").unwrap(),
+            [
+                (Key::LControl, Direction::Press),
+                (Key::Unicode('+'), Direction::Press),
+                (Key::Unicode('+'), Direction::Release),
+                (Key::LControl, Direction::Release),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_sends() {
+        assert_eq!(
+            parse_send("{Shift down}a{Shift up}").unwrap(),
+            [
+                (Key::LShift, Direction::Press),
+                (Key::Unicode('a'), Direction::Click),
+                (Key::LShift, Direction::Release),
+            ]
+        );
+        assert_eq!(
+            parse_send("{Tab 3}").unwrap(),
+            [(Key::Tab, Direction::Click); 3]
+        );
+        assert_eq!(
+            parse_send("{{}{}}").unwrap(),
+            [
+                (Key::Unicode('{'), Direction::Click),
+                (Key::Unicode('}'), Direction::Click),
+            ]
+        );
+        assert_eq!(
+            parse_send("a\r\nb{Enter}").unwrap(),
+            [
+                (Key::Unicode('a'), Direction::Click),
+                (Key::Return, Direction::Click),
+                (Key::Unicode('b'), Direction::Click),
+                (Key::Return, Direction::Click),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        // 位置は 1 から数える
+        assert_eq!(position(parse_send("ab{Enter")), 3);
+        assert_eq!(position(parse_send("ab}")), 3);
+        assert_eq!(position(parse_send("x{Nope}")), 3);
+        assert_eq!(position(parse_send("{a 1x}")), 4);
+        assert_eq!(position(parse_send("{}")), 1);
+
+        assert_eq!(position(parse_combo("ctrl+")), 6);
+        assert_eq!(position(parse_combo("ctrl+nope")), 6);
+        assert_eq!(position(parse_combo("")), 1);
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    pub keyboard_per_sec: f64,
+    pub mouse_button_per_sec: f64,
+    pub mouse_move_per_sec: f64,
+    // 送りきるのにこの時間より長くかかる入力が溜まったら、暴走とみなしてスクリプトを止める
+    pub flood_secs: u64,
+}
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            keyboard_per_sec: 200.0,
+            mouse_button_per_sec: 50.0,
+            mouse_move_per_sec: 1000.0,
+            flood_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    Keyboard,
+    MouseButton,
+    MouseMove,
+}
+
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+impl TokenBucket {
+    // 1秒分までまとめて送れる
+    fn new(rate: f64, now: Instant) -> Self {
+        TokenBucket {
+            rate,
+            tokens: rate,
+            last: now,
+        }
+    }
+
+    // トークンを消費し、使えるようになるまでの待ち時間を返す
+    fn acquire(&mut self, cost: f64, now: Instant) -> Duration {
+        if self.rate <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+
+        self.tokens -= cost;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+pub enum Admission {
+    Now,
+    Wait(Duration),
+    Flood,
+}
+
+pub struct Limiter {
+    keyboard: TokenBucket,
+    mouse_button: TokenBucket,
+    mouse_move: TokenBucket,
+    flood: Duration,
+}
+impl Limiter {
+    pub fn new(limits: &RateLimits, now: Instant) -> Self {
+        Limiter {
+            keyboard: TokenBucket::new(limits.keyboard_per_sec, now),
+            mouse_button: TokenBucket::new(limits.mouse_button_per_sec, now),
+            mouse_move: TokenBucket::new(limits.mouse_move_per_sec, now),
+            flood: Duration::from_secs(limits.flood_secs),
+        }
+    }
+
+    pub fn admit(&mut self, kind: InputKind, cost: u32, now: Instant) -> Admission {
+        let bucket = match kind {
+            InputKind::Keyboard => &mut self.keyboard,
+            InputKind::MouseButton => &mut self.mouse_button,
+            InputKind::MouseMove => &mut self.mouse_move,
+        };
+        // 待ってから次を送る呼び出し側なら、溜まるのは一回分だけ
+        let wait = bucket.acquire(cost as f64, now);
+        if wait.is_zero() {
+            Admission::Now
+        } else if !self.flood.is_zero() && wait >= self.flood {
+            Admission::Flood
+        } else {
+            Admission::Wait(wait)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(per_sec: f64) -> RateLimits {
+        RateLimits {
+            keyboard_per_sec: per_sec,
+            flood_secs: 2,
+            ..RateLimits::default()
+        }
+    }
+
+    #[test]
+    fn bucket_allows_burst_then_paces() {
+        let start = Instant::now();
+        let mut limiter = Limiter::new(&limits(10.0), start);
+        for _ in 0..10 {
+            assert!(matches!(
+                limiter.admit(InputKind::Keyboard, 1, start),
+                Admission::Now
+            ));
+        }
+        let Admission::Wait(wait) = limiter.admit(InputKind::Keyboard, 1, start) else {
+            panic!("expected to be throttled");
+        };
+        assert_eq!(wait, Duration::from_millis(100));
+
+        // 補充されたトークンで再び通る
+        let later = start + Duration::from_millis(300);
+        assert!(matches!(
+            limiter.admit(InputKind::Keyboard, 1, later),
+            Admission::Now
+        ));
+        // 別の種類には影響しない
+        assert!(matches!(
+            limiter.admit(InputKind::MouseMove, 1, start),
+            Admission::Now
+        ));
+    }
+
+    #[test]
+    fn unpaced_demand_is_a_flood() {
+        let start = Instant::now();
+        let mut limiter = Limiter::new(&limits(10.0), start);
+        let mut now = start;
+        let mut flooded = false;
+        // 待ち時間を待たずに 1 秒に 100 回送り続ける
+        for _ in 0..300 {
+            if let Admission::Flood = limiter.admit(InputKind::Keyboard, 1, now) {
+                flooded = true;
+                break;
+            }
+            now += Duration::from_millis(10);
+        }
+        assert!(flooded);
+    }
+
+    // 言われた時間だけ待ってから次を送る
+    fn paced(limiter: &mut Limiter, count: usize, cost: u32, now: &mut Instant) {
+        for _ in 0..count {
+            match limiter.admit(InputKind::Keyboard, cost, *now) {
+                Admission::Now => {}
+                Admission::Wait(wait) => *now += wait,
+                Admission::Flood => panic!("paced input was treated as a flood"),
+            }
+        }
+    }
+
+    #[test]
+    fn long_paced_input_is_not_a_flood() {
+        let start = Instant::now();
+        let mut now = start;
+        let mut limiter = Limiter::new(&RateLimits::default(), start);
+        // keyboard.press を 2000 回
+        paced(&mut limiter, 2000, 1, &mut now);
+        assert!(now - start >= Duration::from_secs(9));
+
+        // 5000 文字の keyboard.type。32 文字ずつ送る
+        let start = now;
+        paced(&mut limiter, 5000 / 32, 32, &mut now);
+        assert!(now - start >= Duration::from_secs(24));
+    }
+
+    #[test]
+    fn paced_input_is_not_a_flood() {
+        let start = Instant::now();
+        let mut limiter = Limiter::new(&limits(10.0), start);
+        for i in 0..100 {
+            let now = start + Duration::from_millis(100 * i);
+            assert!(matches!(
+                limiter.admit(InputKind::Keyboard, 1, now),
+                Admission::Now
+            ));
+        }
+    }
+}
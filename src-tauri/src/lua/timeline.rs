@@ -0,0 +1,290 @@
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    thread::{sleep, yield_now},
+    time::{Duration, Instant},
+};
+
+use enigo::Direction;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    model::{Button, Coordinate},
+    output::Output,
+    sequence::key_from_name,
+};
+
+pub const MACRO_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct MacroFile {
+    pub version: u32,
+    #[serde(default = "default_coordinate")]
+    pub coordinate: String,
+    pub events: Vec<MacroFileEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MacroFileEvent {
+    pub t: u64,
+    #[serde(flatten)]
+    pub action: MacroFileAction,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MacroFileAction {
+    KeyPress { key: String },
+    KeyRelease { key: String },
+    MouseMove { x: i32, y: i32 },
+    MousePress { button: String },
+    MouseRelease { button: String },
+}
+
+fn default_coordinate() -> String {
+    "Abs".to_string()
+}
+
+enum MacroAction {
+    Key(enigo::Key, Direction),
+    MouseMove(i32, i32),
+    Button(enigo::Button, Direction),
+}
+
+pub struct MacroTimeline {
+    coordinate: enigo::Coordinate,
+    events: Vec<(Duration, MacroAction)>,
+}
+impl MacroTimeline {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let file: MacroFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Self::from_file(file)
+    }
+    pub fn from_file(file: MacroFile) -> anyhow::Result<Self> {
+        if file.version != MACRO_VERSION {
+            anyhow::bail!("Unsupported macro version: {}", file.version);
+        }
+
+        let coordinate = Coordinate::from_str(&file.coordinate)
+            .map_err(|_| anyhow::anyhow!("Invalid coordinate: {}", file.coordinate))?
+            .into();
+        let mut events = Vec::with_capacity(file.events.len());
+        for (index, event) in file.events.into_iter().enumerate() {
+            let action = match event.action {
+                MacroFileAction::KeyPress { key } => {
+                    MacroAction::Key(parse_key(&key, index)?, Direction::Press)
+                }
+                MacroFileAction::KeyRelease { key } => {
+                    MacroAction::Key(parse_key(&key, index)?, Direction::Release)
+                }
+                MacroFileAction::MouseMove { x, y } => MacroAction::MouseMove(x, y),
+                MacroFileAction::MousePress { button } => {
+                    MacroAction::Button(parse_button(&button, index)?, Direction::Press)
+                }
+                MacroFileAction::MouseRelease { button } => {
+                    MacroAction::Button(parse_button(&button, index)?, Direction::Release)
+                }
+            };
+            events.push((Duration::from_millis(event.t), action));
+        }
+
+        Ok(MacroTimeline { coordinate, events })
+    }
+
+    pub fn play(
+        &self,
+        output: &mut Output,
+        speed: f64,
+        loops: u32,
+        exit_flag: &AtomicBool,
+    ) -> anyhow::Result<()> {
+        if speed <= 0.0 {
+            anyhow::bail!("Invalid speed: {}", speed);
+        }
+
+        // 停止やエラーで途中で抜けても、押したままのキーとボタンを離す
+        let mut pressed = Pressed::default();
+        let result = self.play_loops(output, speed, loops, exit_flag, &mut pressed);
+        pressed.release(output);
+        result
+    }
+
+    fn play_loops(
+        &self,
+        output: &mut Output,
+        speed: f64,
+        loops: u32,
+        exit_flag: &AtomicBool,
+        pressed: &mut Pressed,
+    ) -> anyhow::Result<()> {
+        let mut count = 0;
+        while loops == 0 || count < loops {
+            let timer = output.timer();
+            for (at, action) in &self.events {
+                if !output.wait(&timer, at.div_f64(speed), exit_flag) {
+                    return Ok(());
+                }
+
+                match action {
+                    MacroAction::Key(key, direction) => output.key(*key, *direction)?,
+                    MacroAction::MouseMove(x, y) => output.move_mouse(*x, *y, self.coordinate)?,
+                    MacroAction::Button(button, direction) => output.button(*button, *direction)?,
+                }
+                pressed.record(action);
+            }
+            count += 1;
+        }
+
+        Ok(())
+    }
+}
+
+// 再生中に押されたままになっているキーとボタン
+#[derive(Default)]
+struct Pressed {
+    keys: Vec<enigo::Key>,
+    buttons: Vec<enigo::Button>,
+}
+impl Pressed {
+    fn record(&mut self, action: &MacroAction) {
+        match *action {
+            MacroAction::Key(key, Direction::Press) if !self.keys.contains(&key) => {
+                self.keys.push(key)
+            }
+            MacroAction::Key(key, Direction::Release) => self.keys.retain(|k| *k != key),
+            MacroAction::Button(button, Direction::Press) if !self.buttons.contains(&button) => {
+                self.buttons.push(button)
+            }
+            MacroAction::Button(button, Direction::Release) => {
+                self.buttons.retain(|b| *b != button)
+            }
+            _ => {}
+        }
+    }
+
+    // 離せなくても残りは離す
+    fn release(&mut self, output: &mut Output) {
+        for key in self.keys.drain(..).rev() {
+            let _ = output.key(key, Direction::Release);
+        }
+        for button in self.buttons.drain(..).rev() {
+            let _ = output.button(button, Direction::Release);
+        }
+    }
+}
+
+fn parse_key(key: &str, index: usize) -> anyhow::Result<enigo::Key> {
+    key_from_name(key).ok_or_else(|| anyhow::anyhow!("Invalid key at event {}: {}", index, key))
+}
+
+fn parse_button(button: &str, index: usize) -> anyhow::Result<enigo::Button> {
+    Button::from_str(button)
+        .ok()
+        .and_then(Button::send)
+        .ok_or_else(|| anyhow::anyhow!("Invalid button at event {}: {}", index, button))
+}
+
+pub(super) fn wait_until(deadline: Instant, exit_flag: &AtomicBool) -> bool {
+    loop {
+        if exit_flag.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        let remaining = deadline - now;
+        if remaining > Duration::from_millis(2) {
+            sleep((remaining - Duration::from_millis(1)).min(Duration::from_millis(10)));
+        } else {
+            yield_now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> anyhow::Result<MacroTimeline> {
+        MacroTimeline::from_file(serde_json::from_str(json)?)
+    }
+
+    #[test]
+    fn accepts_current_version() {
+        let timeline = parse(
+            r#"{"version": 1, "events": [
+                {"t": 0, "type": "key_press", "key": "LShift"},
+                {"t": 10, "type": "mouse_move", "x": 5, "y": 6},
+                {"t": 20, "type": "mouse_press", "button": "Left"},
+                {"t": 30, "type": "mouse_release", "button": "Left"},
+                {"t": 40, "type": "key_release", "key": "LShift"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(timeline.coordinate, enigo::Coordinate::Abs);
+        assert_eq!(timeline.events.len(), 5);
+        assert_eq!(timeline.events[4].0, Duration::from_millis(40));
+        assert!(matches!(
+            timeline.events[0].1,
+            MacroAction::Key(enigo::Key::LShift, Direction::Press)
+        ));
+        assert!(matches!(timeline.events[1].1, MacroAction::MouseMove(5, 6)));
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let err = parse(r#"{"version": 2, "events": []}"#).err().unwrap();
+        assert_eq!(err.to_string(), "Unsupported macro version: 2");
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let err =
+            parse(r#"{"version": 1, "events": [{"t": 0, "type": "key_press", "key": "NotAKey"}]}"#)
+                .err()
+                .unwrap();
+        assert_eq!(err.to_string(), "Invalid key at event 0: NotAKey");
+
+        let err = parse(
+            r#"{"version": 1, "events": [
+                {"t": 0, "type": "mouse_move", "x": 0, "y": 0},
+                {"t": 1, "type": "mouse_press", "button": "Nope"}
+            ]}"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "Invalid button at event 1: Nope");
+    }
+
+    #[test]
+    fn parses_coordinate_mode() {
+        let timeline = parse(r#"{"version": 1, "coordinate": "Rel", "events": []}"#).unwrap();
+        assert_eq!(timeline.coordinate, enigo::Coordinate::Rel);
+
+        let err = parse(r#"{"version": 1, "coordinate": "Screen", "events": []}"#)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Invalid coordinate: Screen");
+    }
+
+    #[test]
+    fn tracks_inputs_left_pressed() {
+        let mut pressed = Pressed::default();
+        for action in [
+            MacroAction::Key(enigo::Key::LShift, Direction::Press),
+            MacroAction::Key(enigo::Key::LShift, Direction::Press),
+            MacroAction::Key(enigo::Key::Control, Direction::Press),
+            MacroAction::Button(enigo::Button::Left, Direction::Press),
+            MacroAction::Key(enigo::Key::LShift, Direction::Release),
+            MacroAction::MouseMove(1, 1),
+        ] {
+            pressed.record(&action);
+        }
+        assert_eq!(pressed.keys, [enigo::Key::Control]);
+        assert_eq!(pressed.buttons, [enigo::Button::Left]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use enigo::{Direction, Key};
+use rand::rngs::StdRng;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{humanize, output::Output};
+
+// 速く打つときに一度に送る文字数。長い文字列でも途中で停止に気づけるよう区切る
+const CHUNK_GRAPHEMES: usize = 32;
+
+#[derive(Debug, PartialEq)]
+enum Chunk<'a> {
+    Text(&'a str),
+    Key(Key),
+}
+
+pub fn type_text(
+    output: &mut Output,
+    text: &str,
+    delay_ms: u64,
+    jitter_ms: u64,
+    rng: &Mutex<StdRng>,
+    exit_flag: &AtomicBool,
+) -> anyhow::Result<()> {
+    let fast = delay_ms == 0 && jitter_ms == 0;
+
+    for chunk in chunks(text, if fast { CHUNK_GRAPHEMES } else { 1 }) {
+        if exit_flag.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        match chunk {
+            Chunk::Text(text) => output.text(text)?,
+            Chunk::Key(key) => output.key(key, Direction::Click)?,
+        }
+        if fast {
+            continue;
+        }
+
+        let delay = if jitter_ms > 0 {
+            humanize::jitter(&mut rng.lock().unwrap(), delay_ms, jitter_ms)
+        } else {
+            Duration::from_millis(delay_ms)
+        };
+        let timer = output.timer();
+        if !output.wait(&timer, delay, exit_flag) {
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+// 送る単位に分ける。改行とタブはキーで送り、文字は書記素の途中で区切らない
+fn chunks(text: &str, max_graphemes: usize) -> Vec<Chunk<'_>> {
+    let mut chunks = Vec::new();
+    let mut run: Option<(usize, usize)> = None;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        let key = match grapheme {
+            "\n" | "\r\n" | "\r" => Some(Key::Return),
+            "\t" => Some(Key::Tab),
+            _ => None,
+        };
+        if let Some((start, count)) = run {
+            if key.is_some() || count == max_graphemes {
+                chunks.push(Chunk::Text(&text[start..index]));
+                run = None;
+            }
+        }
+        match key {
+            Some(key) => chunks.push(Chunk::Key(key)),
+            None => {
+                let (_, count) = run.get_or_insert((index, 0));
+                *count += 1;
+            }
+        }
+    }
+    if let Some((start, _)) = run {
+        chunks.push(Chunk::Text(&text[start..]));
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_line_breaks_and_tabs_to_keys() {
+        assert_eq!(
+            chunks("a\r\nb\rc\nd\te", 32),
+            [
+                Chunk::Text("a"),
+                Chunk::Key(Key::Return),
+                Chunk::Text("b"),
+                Chunk::Key(Key::Return),
+                Chunk::Text("c"),
+                Chunk::Key(Key::Return),
+                Chunk::Text("d"),
+                Chunk::Key(Key::Tab),
+                Chunk::Text("e"),
+            ]
+        );
+        assert_eq!(
+            chunks("\n\n", 32),
+            [Chunk::Key(Key::Return), Chunk::Key(Key::Return)]
+        );
+        assert!(chunks("", 32).is_empty());
+    }
+
+    #[test]
+    fn does_not_split_graphemes() {
+        // 結合文字と ZWJ でつないだ絵文字はそれぞれ一つの書記素
+        let text = "e\u{301}👨\u{200d}👩\u{200d}👧あ";
+        assert_eq!(
+            chunks(text, 1),
+            [
+                Chunk::Text("e\u{301}"),
+                Chunk::Text("👨\u{200d}👩\u{200d}👧"),
+                Chunk::Text("あ"),
+            ]
+        );
+        assert_eq!(
+            chunks(text, 2),
+            [
+                Chunk::Text("e\u{301}👨\u{200d}👩\u{200d}👧"),
+                Chunk::Text("あ"),
+            ]
+        );
+        assert_eq!(chunks(text, 32), [Chunk::Text(text)]);
+    }
+
+    #[test]
+    fn bounds_long_runs() {
+        let text = "x".repeat(CHUNK_GRAPHEMES * 2 + 1);
+        let sizes: Vec<usize> = chunks(&text, CHUNK_GRAPHEMES)
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Text(text) => text.len(),
+                Chunk::Key(_) => 0,
+            })
+            .collect();
+        assert_eq!(sizes, [CHUNK_GRAPHEMES, CHUNK_GRAPHEMES, 1]);
+    }
+}
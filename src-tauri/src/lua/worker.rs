@@ -0,0 +1,556 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::spawn,
+    time::Duration,
+};
+
+use crossbeam::channel as ch;
+use device_query::{DeviceState, Keycode, MouseState};
+use enigo::{Axis, Direction};
+use rand::rngs::StdRng;
+
+use super::{
+    error::InputBackendError,
+    gesture,
+    humanize::{self, HumanizeOptions, SMOOTH_STEP},
+    limits::{Budget, LimitExceeded},
+    model::{Coordinate, Curve, Key},
+    output::Output,
+    pause::PauseState,
+    sequence::{self, KeySequence},
+    throttle::RateLimits,
+    typing, HostEvent, MacroTimeline,
+};
+
+// 一時停止したかを確認する間隔
+const PAUSE_POLL: Duration = Duration::from_millis(10);
+const RESPONSE_POLL: Duration = Duration::from_millis(50);
+
+pub enum LuaEvent {
+    KeyboardPress {
+        key: Key,
+    },
+    KeyboardRelease {
+        key: Key,
+    },
+    KeyboardClick {
+        key: Key,
+        humanize: Option<HumanizeOptions>,
+    },
+    KeyboardIsPressing {
+        key: Keycode,
+        res: ch::Sender<bool>,
+    },
+    KeyboardCharPress {
+        char: char,
+    },
+    KeyboardCharRelease {
+        char: char,
+    },
+    KeyboardCharClick {
+        char: char,
+    },
+    KeyboardSequence {
+        sequence: KeySequence,
+    },
+    KeyboardType {
+        text: String,
+        delay_ms: u64,
+        jitter_ms: u64,
+    },
+    KeyboardPressed {
+        res: ch::Sender<Vec<Keycode>>,
+    },
+    MouseGetPos {
+        res: ch::Sender<(i32, i32)>,
+    },
+    MouseState {
+        res: ch::Sender<MouseState>,
+    },
+    MouseMove {
+        x: i32,
+        y: i32,
+        coordinate: Coordinate,
+    },
+    MousePress {
+        button: enigo::Button,
+    },
+    MouseRelease {
+        button: enigo::Button,
+    },
+    MouseClick {
+        button: enigo::Button,
+        humanize: Option<HumanizeOptions>,
+    },
+    MouseMoveSmooth {
+        x: i32,
+        y: i32,
+        coordinate: Coordinate,
+        duration: Duration,
+        curve: Curve,
+    },
+    MouseClickAt {
+        x: i32,
+        y: i32,
+        button: enigo::Button,
+    },
+    MouseMultiClick {
+        button: enigo::Button,
+        count: u32,
+        interval: Duration,
+    },
+    MouseDrag {
+        from: (i32, i32),
+        to: (i32, i32),
+        button: enigo::Button,
+        duration: Duration,
+    },
+    MouseScroll {
+        dx: i32,
+        dy: i32,
+        duration: Duration,
+    },
+    MouseIsPressing {
+        index: usize,
+        res: ch::Sender<bool>,
+    },
+    MacroPlay {
+        timeline: MacroTimeline,
+        speed: f64,
+        loops: u32,
+    },
+}
+
+impl LuaEvent {
+    // エラーメッセージに使う
+    fn name(&self) -> &'static str {
+        match self {
+            LuaEvent::KeyboardPress { .. } => "keyboard.press",
+            LuaEvent::KeyboardRelease { .. } => "keyboard.release",
+            LuaEvent::KeyboardClick { .. } => "keyboard.click",
+            LuaEvent::KeyboardIsPressing { .. } => "keyboard.is_pressing",
+            LuaEvent::KeyboardCharPress { .. } => "keyboard.char.press",
+            LuaEvent::KeyboardCharRelease { .. } => "keyboard.char.release",
+            LuaEvent::KeyboardCharClick { .. } => "keyboard.char.click",
+            LuaEvent::KeyboardSequence { .. } => "keyboard.send",
+            LuaEvent::KeyboardType { .. } => "keyboard.type",
+            LuaEvent::KeyboardPressed { .. } => "keyboard.pressed",
+            LuaEvent::MouseGetPos { .. } => "mouse.get_pos",
+            LuaEvent::MouseState { .. } => "mouse.state",
+            LuaEvent::MouseMove { .. } => "mouse.move",
+            LuaEvent::MousePress { .. } => "mouse.press",
+            LuaEvent::MouseRelease { .. } => "mouse.release",
+            LuaEvent::MouseClick { .. } => "mouse.click",
+            LuaEvent::MouseMoveSmooth { .. } => "mouse.move_smooth",
+            LuaEvent::MouseClickAt { .. } => "mouse.click_at",
+            LuaEvent::MouseMultiClick { .. } => "mouse.multi_click",
+            LuaEvent::MouseDrag { .. } => "mouse.drag",
+            LuaEvent::MouseScroll { .. } => "mouse.scroll",
+            LuaEvent::MouseIsPressing { .. } => "mouse.is_pressing",
+            LuaEvent::MacroPlay { .. } => "macro.play",
+        }
+    }
+    // 入力を送るイベント。停止後や一時停止中は送らない
+    fn is_input(&self) -> bool {
+        !matches!(
+            self,
+            LuaEvent::KeyboardIsPressing { .. }
+                | LuaEvent::KeyboardPressed { .. }
+                | LuaEvent::MouseGetPos { .. }
+                | LuaEvent::MouseState { .. }
+                | LuaEvent::MouseIsPressing { .. }
+        )
+    }
+}
+
+type Ack = Result<(), InputBackendError>;
+
+// 処理が終わると ack に結果が返る
+struct Command {
+    event: LuaEvent,
+    ack: ch::Sender<Ack>,
+}
+
+// ワーカーを起動し直すときにも使う
+#[derive(Clone)]
+pub struct WorkerContext {
+    pub rate_limits: RateLimits,
+    pub exit_flag: Arc<AtomicBool>,
+    pub abandoned: Arc<AtomicBool>,
+    pub budget: Arc<Budget>,
+    pub pause: Arc<PauseState>,
+    pub host: ch::Sender<HostEvent>,
+    pub rng: Arc<Mutex<StdRng>>,
+}
+
+// 入力を送るスレッドへの窓口。スレッドが終了していたら次の呼び出しで起動し直す
+pub struct Worker {
+    context: WorkerContext,
+    sender: Mutex<Option<ch::Sender<Command>>>,
+}
+impl Worker {
+    pub fn new(context: WorkerContext) -> Self {
+        Worker {
+            context,
+            sender: Mutex::new(None),
+        }
+    }
+
+    pub fn context(&self) -> &WorkerContext {
+        &self.context
+    }
+
+    // 処理が終わるまで待つ。停止された場合は何もせずに戻る
+    pub fn send(&self, event: LuaEvent) -> mlua::Result<()> {
+        self.call(event).map(|_| ())
+    }
+    // res に返ってきた値を受け取る。停止された場合は None
+    pub fn request<T>(
+        &self,
+        event: impl FnOnce(ch::Sender<T>) -> LuaEvent,
+    ) -> mlua::Result<Option<T>> {
+        let (res, receiver) = ch::bounded(1);
+        let event = event(res);
+        let event_name = event.name();
+        if self.call(event)?.is_none() {
+            return Ok(None);
+        }
+        receiver
+            .try_recv()
+            .map(Some)
+            .map_err(|_| backend_error(event_name, "the input worker did not return a value"))
+    }
+
+    fn call(&self, event: LuaEvent) -> mlua::Result<Option<()>> {
+        if self.context.exit_flag.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        let name = event.name();
+        let (ack, receiver) = ch::bounded(1);
+        let mut command = Command { event, ack };
+        // 一度だけ起動し直して送り直す
+        for _ in 0..2 {
+            let sender = self.sender();
+            match sender.send(command) {
+                Ok(()) => {
+                    return match wait_ack(&receiver, &self.context.exit_flag) {
+                        Ok(ack) => ack.transpose().map_err(mlua::Error::external),
+                        Err(()) => Err(backend_error(
+                            name,
+                            "the input worker stopped unexpectedly and will be restarted",
+                        )),
+                    };
+                }
+                Err(ch::SendError(returned)) => {
+                    command = returned;
+                    self.sender.lock().unwrap().take();
+                }
+            }
+        }
+
+        Err(backend_error(name, "failed to start the input worker"))
+    }
+
+    fn sender(&self) -> ch::Sender<Command> {
+        let mut sender = self.sender.lock().unwrap();
+        sender
+            .get_or_insert_with(|| {
+                // 呼び出しごとに処理が終わるのを待つので、溜まるのは一つだけ
+                let (sender, receiver) = ch::bounded(1);
+                let context = self.context.clone();
+                spawn(move || run(context, receiver));
+                sender
+            })
+            .clone()
+    }
+}
+
+fn backend_error(function: &'static str, message: &str) -> mlua::Error {
+    mlua::Error::external(InputBackendError {
+        function,
+        message: message.to_string(),
+    })
+}
+
+// 停止されたら Ok(None)、ワーカーが応答せずに終了したら Err
+fn wait_ack(receiver: &ch::Receiver<Ack>, exit_flag: &AtomicBool) -> Result<Option<Ack>, ()> {
+    loop {
+        if exit_flag.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        match receiver.recv_timeout(RESPONSE_POLL) {
+            Ok(ack) => return Ok(Some(ack)),
+            Err(ch::RecvTimeoutError::Timeout) => continue,
+            Err(ch::RecvTimeoutError::Disconnected) => return Err(()),
+        }
+    }
+}
+
+fn run(context: WorkerContext, receiver: ch::Receiver<Command>) {
+    // 送った入力の数で制限する
+    let output = match Output::new() {
+        Ok(output) => output.with_script(
+            &context.rate_limits,
+            Arc::clone(&context.exit_flag),
+            Arc::clone(&context.pause),
+        ),
+        Err(err) => {
+            // 次の呼び出しで接続し直す
+            if let Ok(command) = receiver.recv() {
+                let _ = command.ack.send(Err(InputBackendError {
+                    function: command.event.name(),
+                    message: format!("failed to connect to the input backend: {}", err),
+                }));
+            }
+            return;
+        }
+    };
+    let mut state = WorkerState {
+        context,
+        output,
+        device: DeviceState::new(),
+    };
+
+    loop {
+        let command = receiver.recv_timeout(PAUSE_POLL);
+        let exit_flag = &state.context.exit_flag;
+        if exit_flag.load(Ordering::SeqCst) {
+            // 停止したら押しているキーを離し、切り離されたら終わる
+            state.output.release_held();
+            if state.context.abandoned.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        let Command { event, ack } = match command {
+            Ok(command) => command,
+            Err(ch::RecvTimeoutError::Timeout) => {
+                state.output.sync_pause();
+                continue;
+            }
+            Err(ch::RecvTimeoutError::Disconnected) => break,
+        };
+
+        let function = event.name();
+        let res = state.handle(event);
+        // 制限され続けて途中で抜けたときは、エラーではなく制限超過として止める
+        let res = if state.output.take_flood() {
+            state.stop_flood();
+            Ok(())
+        } else {
+            res.map_err(|err| InputBackendError {
+                function,
+                message: format!("{:#}", err),
+            })
+        };
+        let _ = ack.send(res);
+    }
+}
+
+struct WorkerState {
+    context: WorkerContext,
+    output: Output,
+    device: DeviceState,
+}
+impl WorkerState {
+    fn handle(&mut self, event: LuaEvent) -> anyhow::Result<()> {
+        let exit_flag = Arc::clone(&self.context.exit_flag);
+
+        // 停止後に残っている入力は送らない
+        if exit_flag.load(Ordering::SeqCst) && event.is_input() {
+            return Ok(());
+        }
+        // 一時停止中は入力を送らずに再開を待つ。長くかかる処理も送るたびに待つ
+        self.output.sync_pause();
+        if event.is_input() && !self.output.wait_resumed(&exit_flag) {
+            return Ok(());
+        }
+
+        let WorkerState {
+            context,
+            output,
+            device,
+        } = self;
+        let rng = &context.rng;
+        match event {
+            LuaEvent::KeyboardPress { key } => {
+                output.key(key.into(), Direction::Press)?;
+            }
+            LuaEvent::KeyboardRelease { key } => {
+                output.key(key.into(), Direction::Release)?;
+            }
+            LuaEvent::KeyboardClick { key, humanize } => {
+                if let Some(humanize) = humanize {
+                    let key = key.into();
+                    let hold = humanize.hold(&mut rng.lock().unwrap());
+                    let timer = output.timer();
+                    output.key(key, Direction::Press)?;
+                    output.wait(&timer, hold, &exit_flag);
+                    output.key(key, Direction::Release)?;
+                } else {
+                    output.key(key.into(), Direction::Click)?;
+                }
+            }
+            LuaEvent::KeyboardIsPressing { key, res } => {
+                let _ = res.send(device.query_keymap().contains(&key));
+            }
+
+            LuaEvent::KeyboardCharPress { char } => {
+                output.key(enigo::Key::Unicode(char), Direction::Press)?;
+            }
+            LuaEvent::KeyboardCharRelease { char } => {
+                output.key(enigo::Key::Unicode(char), Direction::Release)?;
+            }
+            LuaEvent::KeyboardCharClick { char } => {
+                output.key(enigo::Key::Unicode(char), Direction::Click)?;
+            }
+            LuaEvent::KeyboardSequence { sequence } => {
+                sequence::run(output, &sequence)?;
+            }
+            LuaEvent::KeyboardType {
+                text,
+                delay_ms,
+                jitter_ms,
+            } => {
+                typing::type_text(output, &text, delay_ms, jitter_ms, rng, &exit_flag)?;
+            }
+            LuaEvent::KeyboardPressed { res } => {
+                let _ = res.send(device.query_keymap());
+            }
+            LuaEvent::MouseGetPos { res } => {
+                let _ = res.send(device.query_pointer().coords);
+            }
+            LuaEvent::MouseState { res } => {
+                let _ = res.send(device.query_pointer());
+            }
+            LuaEvent::MouseMove { x, y, coordinate } => {
+                output.move_mouse(x, y, coordinate.into())?;
+            }
+            LuaEvent::MousePress { button } => {
+                output.button(button, Direction::Press)?;
+            }
+            LuaEvent::MouseRelease { button } => {
+                output.button(button, Direction::Release)?;
+            }
+            LuaEvent::MouseClick { button, humanize } => {
+                if let Some(humanize) = humanize {
+                    let ((dx, dy), hold) = {
+                        let mut rng = rng.lock().unwrap();
+                        (humanize.offset(&mut rng), humanize.hold(&mut rng))
+                    };
+                    output.move_mouse(dx, dy, enigo::Coordinate::Rel)?;
+                    let timer = output.timer();
+                    output.button(button, Direction::Press)?;
+                    output.wait(&timer, hold, &exit_flag);
+                    output.button(button, Direction::Release)?;
+                    output.move_mouse(-dx, -dy, enigo::Coordinate::Rel)?;
+                } else {
+                    output.button(button, Direction::Click)?;
+                }
+            }
+            LuaEvent::MouseMoveSmooth {
+                x,
+                y,
+                coordinate,
+                duration,
+                curve,
+            } => {
+                let from = output.location()?;
+                let to = match coordinate {
+                    Coordinate::Abs => (x, y),
+                    Coordinate::Rel => (from.0 + x, from.1 + y),
+                };
+                let path =
+                    humanize::smooth_path(&mut rng.lock().unwrap(), from, to, duration, curve);
+
+                let timer = output.timer();
+                for (step, (x, y)) in path.into_iter().enumerate() {
+                    if !output.wait(&timer, SMOOTH_STEP * step as u32, &exit_flag) {
+                        break;
+                    }
+                    output.move_mouse(x, y, enigo::Coordinate::Abs)?;
+                }
+            }
+            LuaEvent::MouseClickAt { x, y, button } => {
+                output.move_mouse(x, y, enigo::Coordinate::Abs)?;
+                output.button(button, Direction::Click)?;
+            }
+            LuaEvent::MouseMultiClick {
+                button,
+                count,
+                interval,
+            } => {
+                gesture::multi_click(output, button, count, interval, &exit_flag)?;
+            }
+            LuaEvent::MouseDrag {
+                from,
+                to,
+                button,
+                duration,
+            } => {
+                gesture::drag(output, rng, from, to, button, duration, &exit_flag)?;
+            }
+            LuaEvent::MouseScroll { dx, dy, duration } => {
+                if duration.is_zero() {
+                    if dx != 0 {
+                        output.scroll(dx, Axis::Horizontal)?;
+                    }
+                    if dy != 0 {
+                        output.scroll(dy, Axis::Vertical)?;
+                    }
+                } else {
+                    let steps = humanize::scroll_steps(dx, dy);
+                    let interval = duration / steps.len().max(1) as u32;
+                    let timer = output.timer();
+                    for (step, (x, y)) in steps.into_iter().enumerate() {
+                        if !output.wait(&timer, interval * step as u32, &exit_flag) {
+                            break;
+                        }
+                        if x != 0 {
+                            output.scroll(x, Axis::Horizontal)?;
+                        }
+                        if y != 0 {
+                            output.scroll(y, Axis::Vertical)?;
+                        }
+                    }
+                }
+            }
+            LuaEvent::MouseIsPressing { index, res } => {
+                let _ = res.send(
+                    device
+                        .query_pointer()
+                        .button_pressed
+                        .get(index)
+                        .copied()
+                        .unwrap_or(false),
+                );
+            }
+            LuaEvent::MacroPlay {
+                timeline,
+                speed,
+                loops,
+            } => {
+                timeline.play(output, speed, loops, &exit_flag)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // 送りきれないほどの入力が溜まったので止める
+    fn stop_flood(&self) {
+        let flood_secs = self.context.rate_limits.flood_secs;
+        self.context
+            .budget
+            .exceed(LimitExceeded::InputFlood(flood_secs));
+        self.context.exit_flag.store(true, Ordering::SeqCst);
+        let _ = self.context.host.send(HostEvent::Notify {
+            title: "スクリプトを停止しました".to_string(),
+            body: "送りきれないほどの入力が溜まったため、スクリプトを停止しました".to_string(),
+            icon: None,
+        });
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use tauri::{AppHandle, Manager, Url, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+
+static PROMPT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub fn show_prompt<F>(
+    app: &AppHandle,
+    title: &str,
+    message: &str,
+    default: &str,
+    f: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce(Option<String>) + Send + 'static,
+{
+    let label = format!("prompt-{}", PROMPT_ID.fetch_add(1, Ordering::SeqCst));
+    let html = fill(
+        include_str!("prompt.html"),
+        &[
+            ("message", escape_html(message)),
+            ("default", escape_html(default)),
+        ],
+    );
+    let url = Url::parse(&format!(
+        "data:text/html;charset=utf-8,{}",
+        encode_uri(&html)
+    ))?;
+
+    let f = Arc::new(Mutex::new(Some(f)));
+    let window = WebviewWindowBuilder::new(app, &label, WebviewUrl::External(url))
+        .title(title)
+        .inner_size(400.0, 160.0)
+        .resizable(false)
+        .always_on_top(true)
+        .center()
+        .on_navigation({
+            let app = app.clone();
+            let label = label.clone();
+            let f = Arc::clone(&f);
+            move |url| {
+                if url.scheme() != "yam" {
+                    return true;
+                }
+
+                let value = match url.path() {
+                    "/ok" => url
+                        .query_pairs()
+                        .find(|(key, _)| key == "value")
+                        .map(|(_, value)| value.to_string()),
+                    _ => None,
+                };
+                if let Some(f) = f.lock().unwrap().take() {
+                    f(value);
+                }
+                if let Some(window) = app.get_webview_window(&label) {
+                    let _ = window.close();
+                }
+
+                false
+            }
+        })
+        .build()?;
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            if let Some(f) = f.lock().unwrap().take() {
+                f(None);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+// 置き換えた値の中の {...} をさらに置き換えないよう、一度に埋める
+pub(crate) fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut html = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        html.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        match values.iter().find(|(key, _)| {
+            rest.strip_prefix(key)
+                .is_some_and(|after| after.starts_with('}'))
+        }) {
+            Some((key, value)) => {
+                html.push_str(value);
+                rest = &rest[key.len() + 1..];
+            }
+            None => html.push('{'),
+        }
+    }
+    html.push_str(rest);
+    html
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn encode_uri(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_does_not_replace_inside_values() {
+        let html = fill(
+            "<p>{message}</p><input value=\"{default}\">{unknown}",
+            &[
+                ("message", "{default}".to_string()),
+                ("default", "a{b}".to_string()),
+            ],
+        );
+        assert_eq!(html, "<p>{default}</p><input value=\"a{b}\">{unknown}");
+    }
+}
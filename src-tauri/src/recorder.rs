@@ -0,0 +1,394 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{sleep, spawn},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use device_query::{DeviceState, Keycode};
+
+use crate::lua::{Button, Key, MacroFile, MacroFileAction, MacroFileEvent, MACRO_VERSION};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub enum RecordedEventKind {
+    KeyPress(Keycode),
+    KeyRelease(Keycode),
+    MouseMove(i32, i32),
+    MousePress(usize),
+    MouseRelease(usize),
+}
+
+pub struct RecordedEvent {
+    pub at: Duration,
+    pub kind: RecordedEventKind,
+}
+
+pub struct Recorder {
+    stop_flag: Arc<AtomicBool>,
+}
+impl Recorder {
+    pub fn start<F>(stop_key: Keycode, f: F) -> Self
+    where
+        F: FnOnce(Vec<RecordedEvent>) + Send + 'static,
+    {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        {
+            let stop_flag = Arc::clone(&stop_flag);
+            spawn(move || {
+                let state = DeviceState::new();
+                let start = Instant::now();
+                let mut events = Vec::new();
+
+                let mut keys = state.query_keymap();
+                let mouse = state.query_pointer();
+                let mut coords = mouse.coords;
+                let mut buttons = mouse.button_pressed;
+                events.push(RecordedEvent {
+                    at: Duration::ZERO,
+                    kind: RecordedEventKind::MouseMove(coords.0, coords.1),
+                });
+
+                while !stop_flag.load(Ordering::SeqCst) {
+                    let now_keys = state.query_keymap();
+                    if now_keys.contains(&stop_key) && !keys.contains(&stop_key) {
+                        break;
+                    }
+                    let mouse = state.query_pointer();
+                    let at = start.elapsed();
+
+                    if mouse.coords != coords {
+                        coords = mouse.coords;
+                        events.push(RecordedEvent {
+                            at,
+                            kind: RecordedEventKind::MouseMove(coords.0, coords.1),
+                        });
+                    }
+                    for (index, (&now, &before)) in
+                        mouse.button_pressed.iter().zip(&buttons).enumerate()
+                    {
+                        if now != before {
+                            events.push(RecordedEvent {
+                                at,
+                                kind: if now {
+                                    RecordedEventKind::MousePress(index)
+                                } else {
+                                    RecordedEventKind::MouseRelease(index)
+                                },
+                            });
+                        }
+                    }
+                    for key in now_keys.iter().filter(|key| !keys.contains(key)) {
+                        if *key != stop_key {
+                            events.push(RecordedEvent {
+                                at,
+                                kind: RecordedEventKind::KeyPress(*key),
+                            });
+                        }
+                    }
+                    for key in keys.iter().filter(|key| !now_keys.contains(key)) {
+                        if *key != stop_key {
+                            events.push(RecordedEvent {
+                                at,
+                                kind: RecordedEventKind::KeyRelease(*key),
+                            });
+                        }
+                    }
+
+                    keys = now_keys;
+                    buttons = mouse.button_pressed;
+                    sleep(POLL_INTERVAL);
+                }
+
+                let at = start.elapsed();
+                for key in keys.into_iter().filter(|key| *key != stop_key) {
+                    events.push(RecordedEvent {
+                        at,
+                        kind: RecordedEventKind::KeyRelease(key),
+                    });
+                }
+                for (index, pressed) in buttons.into_iter().enumerate() {
+                    if pressed {
+                        events.push(RecordedEvent {
+                            at,
+                            kind: RecordedEventKind::MouseRelease(index),
+                        });
+                    }
+                }
+
+                f(events);
+            });
+        }
+
+        Recorder { stop_flag }
+    }
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+pub fn to_lua_script(title: &str, events: &[RecordedEvent]) -> String {
+    let duration = events.last().map_or(Duration::ZERO, |event| event.at);
+
+    let mut script = String::new();
+    let _ = writeln!(script, "--[[ {} ]]", title);
+    let _ = writeln!(script, "-- permissions: input");
+    let _ = writeln!(
+        script,
+        "-- recorded: {}",
+        format_timestamp(SystemTime::now())
+    );
+    let _ = writeln!(script, "-- events: {}", events.len());
+    let _ = writeln!(script, "-- duration: {:.2}s", duration.as_secs_f64());
+    let _ = writeln!(script);
+    let _ = writeln!(script, "function Main()");
+
+    let mut last = Duration::ZERO;
+    for event in events {
+        let delay = (event.at - last).as_millis();
+        if delay > 0 {
+            let _ = writeln!(script, "    sleep({})", delay);
+            last = event.at;
+        }
+
+        let line = match &event.kind {
+            RecordedEventKind::KeyPress(key) => key_call(*key, "press"),
+            RecordedEventKind::KeyRelease(key) => key_call(*key, "release"),
+            RecordedEventKind::MouseMove(x, y) => format!("mouse.move({}, {}, \"Abs\")", x, y),
+            RecordedEventKind::MousePress(index) => button_call(*index, "press"),
+            RecordedEventKind::MouseRelease(index) => button_call(*index, "release"),
+        };
+        let _ = writeln!(script, "    {}", line);
+    }
+
+    let _ = writeln!(script, "end");
+    script
+}
+
+pub fn to_macro_file(events: &[RecordedEvent]) -> MacroFile {
+    MacroFile {
+        version: MACRO_VERSION,
+        coordinate: "Abs".to_string(),
+        events: events
+            .iter()
+            .filter_map(|event| {
+                let action = match &event.kind {
+                    RecordedEventKind::KeyPress(key) => MacroFileAction::KeyPress {
+                        key: key_string(*key)?,
+                    },
+                    RecordedEventKind::KeyRelease(key) => MacroFileAction::KeyRelease {
+                        key: key_string(*key)?,
+                    },
+                    RecordedEventKind::MouseMove(x, y) => {
+                        MacroFileAction::MouseMove { x: *x, y: *y }
+                    }
+                    RecordedEventKind::MousePress(index) => MacroFileAction::MousePress {
+                        button: button_name(*index)?.to_string(),
+                    },
+                    RecordedEventKind::MouseRelease(index) => MacroFileAction::MouseRelease {
+                        button: button_name(*index)?.to_string(),
+                    },
+                };
+
+                Some(MacroFileEvent {
+                    t: event.at.as_millis() as u64,
+                    action,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn key_string(key: Keycode) -> Option<String> {
+    Key::from_keycode(key).map(|key| key.name().to_string())
+}
+
+fn key_call(key: Keycode, method: &str) -> String {
+    if let Some(key) = Key::from_keycode(key) {
+        format!("keyboard.{}(\"{}\")", method, key.name())
+    } else {
+        format!("-- unsupported key: {}", key)
+    }
+}
+
+fn button_call(index: usize, method: &str) -> String {
+    if let Some(name) = button_name(index) {
+        format!("mouse.{}(\"{}\")", method, name)
+    } else {
+        format!("-- unsupported button: {}", index)
+    }
+}
+
+fn button_name(index: usize) -> Option<&'static str> {
+    Button::from_index(index).map(Button::name)
+}
+
+pub fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(ms: u64, kind: RecordedEventKind) -> RecordedEvent {
+        RecordedEvent {
+            at: Duration::from_millis(ms),
+            kind,
+        }
+    }
+
+    fn body(script: &str) -> Vec<&str> {
+        script
+            .lines()
+            .skip_while(|line| *line != "function Main()")
+            .collect()
+    }
+
+    #[test]
+    fn writes_header() {
+        let script = to_lua_script(
+            "Recorded",
+            &[
+                event(0, RecordedEventKind::MouseMove(1, 2)),
+                event(1500, RecordedEventKind::MouseMove(3, 4)),
+            ],
+        );
+        let header: Vec<&str> = script.lines().take(6).collect();
+        assert_eq!(header[0], "--[[ Recorded ]]");
+        assert_eq!(header[1], "-- permissions: input");
+        assert!(header[2].starts_with("-- recorded: ") && header[2].ends_with(" UTC"));
+        assert_eq!(header[3], "-- events: 2");
+        assert_eq!(header[4], "-- duration: 1.50s");
+        assert_eq!(header[5], "");
+
+        let empty = to_lua_script("Empty", &[]);
+        assert!(empty.contains("-- events: 0\n-- duration: 0.00s\n"));
+        assert_eq!(body(&empty), ["function Main()", "end"]);
+    }
+
+    #[test]
+    fn coalesces_sleeps() {
+        // 1ms に満たない間隔は捨てずに次の sleep にまとめる
+        let script = to_lua_script(
+            "Recorded",
+            &[
+                event(0, RecordedEventKind::MouseMove(0, 0)),
+                event(0, RecordedEventKind::MouseMove(1, 0)),
+                event(10, RecordedEventKind::MouseMove(2, 0)),
+                event(10, RecordedEventKind::MouseMove(3, 0)),
+            ],
+        );
+        let mut events: Vec<RecordedEvent> = (0..4)
+            .map(|i| RecordedEvent {
+                at: Duration::from_micros(400 * i),
+                kind: RecordedEventKind::MouseMove(i as i32, 0),
+            })
+            .collect();
+        events.push(event(5, RecordedEventKind::MouseMove(9, 9)));
+        let fine = to_lua_script("Recorded", &events);
+
+        assert_eq!(
+            body(&script),
+            [
+                "function Main()",
+                "    mouse.move(0, 0, \"Abs\")",
+                "    mouse.move(1, 0, \"Abs\")",
+                "    sleep(10)",
+                "    mouse.move(2, 0, \"Abs\")",
+                "    mouse.move(3, 0, \"Abs\")",
+                "end",
+            ]
+        );
+        assert_eq!(
+            body(&fine),
+            [
+                "function Main()",
+                "    mouse.move(0, 0, \"Abs\")",
+                "    mouse.move(1, 0, \"Abs\")",
+                "    mouse.move(2, 0, \"Abs\")",
+                "    sleep(1)",
+                "    mouse.move(3, 0, \"Abs\")",
+                "    sleep(3)",
+                "    mouse.move(9, 9, \"Abs\")",
+                "end",
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_key_and_button_calls() {
+        let left = Button::Left.index().unwrap();
+        let script = to_lua_script(
+            "Recorded",
+            &[
+                event(0, RecordedEventKind::KeyPress(Keycode::LShift)),
+                event(0, RecordedEventKind::KeyPress(Keycode::A)),
+                event(0, RecordedEventKind::KeyRelease(Keycode::A)),
+                event(0, RecordedEventKind::KeyRelease(Keycode::LShift)),
+                event(0, RecordedEventKind::MousePress(left)),
+                event(0, RecordedEventKind::MouseRelease(left)),
+                event(0, RecordedEventKind::MousePress(99)),
+            ],
+        );
+        assert_eq!(
+            body(&script),
+            [
+                "function Main()",
+                "    keyboard.press(\"LShift\")",
+                "    keyboard.press(\"A\")",
+                "    keyboard.release(\"A\")",
+                "    keyboard.release(\"LShift\")",
+                "    mouse.press(\"Left\")",
+                "    mouse.release(\"Left\")",
+                "    -- unsupported button: 99",
+                "end",
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        // うるう日
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29 00:00:00 UTC"
+        );
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "2023-11-14 22:13:20 UTC"
+        );
+        // エポックより前は 0 として扱う
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH - Duration::from_secs(1)),
+            "1970-01-01 00:00:00 UTC"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lua::{Corner, Limits, RateLimits, UserInputPolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Lua,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub notifications: bool,
+    pub record_stop_key: String,
+    pub pause_key: String,
+    pub record_format: RecordFormat,
+    pub limits: Limits,
+    pub rate_limits: RateLimits,
+    // None で無効
+    pub failsafe: Option<Corner>,
+    pub on_user_input: UserInputPolicy,
+    // 停止を要求してから、応答しないスクリプトを切り離すまでの時間
+    pub stop_timeout_ms: u64,
+    // エラー位置を開くコマンド。{file} と {line} が置き換えられる
+    pub editor: String,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            notifications: false,
+            record_stop_key: "F12".to_string(),
+            pause_key: "F8".to_string(),
+            record_format: RecordFormat::Lua,
+            limits: Limits::default(),
+            rate_limits: RateLimits::default(),
+            failsafe: Some(Corner::TopLeft),
+            on_user_input: UserInputPolicy::default(),
+            stop_timeout_ms: 3000,
+            editor: "code --goto {file}:{line}".to_string(),
+        }
+    }
+}
+impl Settings {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Settings::default());
+        }
+
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+    // 手で編集して壊れていても起動できるよう、読めなければ既定の設定を使う
+    pub fn load_or_default<P: AsRef<Path>>(path: P, on_error: impl FnOnce(anyhow::Error)) -> Self {
+        Settings::load(path).unwrap_or_else(|err| {
+            on_error(err);
+            Settings::default()
+        })
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn falls_back_to_default_when_malformed() {
+        let path = env::temp_dir().join(format!("yam-settings-{}.json", std::process::id()));
+        fs::write(&path, r#"{"notifications": tru"#).unwrap();
+
+        let mut error = None;
+        let settings = Settings::load_or_default(&path, |err| error = Some(err));
+        fs::remove_file(&path).unwrap();
+
+        assert!(error.is_some());
+        assert_eq!(settings.pause_key, Settings::default().pause_key);
+        assert_eq!(settings.notifications, Settings::default().notifications);
+
+        // ないときはエラーにしない
+        let settings = Settings::load_or_default(&path, |err| panic!("{}", err));
+        assert_eq!(settings.record_format, RecordFormat::Lua);
+    }
+}